@@ -0,0 +1,204 @@
+//! estimated bounds of objects,
+//! used to skip objects which are not in the image.
+
+use crate::path::Segment;
+
+/// An axis aligned rectangle, which contains everything an object draws.
+/// Bounds are empty, when nothing is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Bounds {
+    pub fn new(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Bounds {
+        Bounds {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    /// bounds of nothing
+    pub fn empty() -> Bounds {
+        Bounds::new(
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        )
+    }
+
+    /// a square around the center
+    pub fn around(radius: f64) -> Bounds {
+        let radius = radius.abs();
+        Bounds::new(-radius, -radius, radius, radius)
+    }
+
+    /// a rectangle around the center
+    pub fn rectangle(width: f64, height: f64) -> Bounds {
+        let (half_width, half_height) = (width.abs() / 2.0, height.abs() / 2.0);
+        Bounds::new(-half_width, -half_height, half_width, half_height)
+    }
+
+    /// bounds of all points
+    pub fn of_points<I: IntoIterator<Item = (f64, f64)>>(points: I) -> Bounds {
+        points.into_iter().fold(Bounds::empty(), |bounds, (x, y)| {
+            Bounds::new(
+                bounds.min_x.min(x),
+                bounds.min_y.min(y),
+                bounds.max_x.max(x),
+                bounds.max_y.max(y),
+            )
+        })
+    }
+
+    /// bounds of a path, splines are always inside of their control points
+    pub fn of_segments(segments: &[Segment]) -> Bounds {
+        let mut points = Vec::new();
+        for segment in segments.iter() {
+            match segment {
+                Segment::MoveTo(x, y) | Segment::LineTo(x, y) => points.push((*x, *y)),
+                Segment::CurveTo(x1, y1, x2, y2, x, y) => {
+                    points.extend(vec![(*x1, *y1), (*x2, *y2), (*x, *y)])
+                }
+                Segment::ClosePath => (),
+            }
+        }
+        Bounds::of_points(points)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !(self.min_x <= self.max_x && self.min_y <= self.max_y)
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds::new(
+            self.min_x.min(other.min_x),
+            self.min_y.min(other.min_y),
+            self.max_x.max(other.max_x),
+            self.max_y.max(other.max_y),
+        )
+    }
+
+    pub fn intersects(&self, other: &Bounds) -> bool {
+        !self.is_empty()
+            && !other.is_empty()
+            && self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+    }
+
+    /// the corners (empty bounds have none)
+    pub fn corners(&self) -> Vec<(f64, f64)> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        vec![
+            (self.min_x, self.min_y),
+            (self.max_x, self.min_y),
+            (self.max_x, self.max_y),
+            (self.min_x, self.max_y),
+        ]
+    }
+
+    /// bounds of the corners moved by the function
+    pub fn map<F: Fn((f64, f64)) -> (f64, f64)>(&self, function: F) -> Bounds {
+        Bounds::of_points(self.corners().into_iter().map(function))
+    }
+
+    pub fn translate(&self, x: f64, y: f64) -> Bounds {
+        self.map(|(px, py)| (px + x, py + y))
+    }
+
+    pub fn scale(&self, factor: f64) -> Bounds {
+        self.map(|(x, y)| (x * factor, y * factor))
+    }
+
+    /// rotate around the center (angle in radian)
+    pub fn rotate(&self, angle: f64) -> Bounds {
+        let (sin, cos) = angle.sin_cos();
+        self.map(|(x, y)| (x * cos - y * sin, x * sin + y * cos))
+    }
+
+    /// add the margin on all sides
+    pub fn expand(&self, margin: f64) -> Bounds {
+        if self.is_empty() {
+            return *self;
+        }
+        let margin = margin.max(0.0);
+        Bounds::new(
+            self.min_x - margin,
+            self.min_y - margin,
+            self.max_x + margin,
+            self.max_y + margin,
+        )
+    }
+
+    /// bounds of these bounds, moved to every point of `other`
+    pub fn spread(&self, other: &Bounds) -> Bounds {
+        if self.is_empty() || other.is_empty() {
+            return Bounds::empty();
+        }
+        Bounds::new(
+            self.min_x + other.min_x,
+            self.min_y + other.min_y,
+            self.max_x + other.max_x,
+            self.max_y + other.max_y,
+        )
+    }
+
+    /// bounds for any rotation around the center
+    pub fn any_rotation(&self) -> Bounds {
+        if self.is_empty() {
+            return *self;
+        }
+        let radius = self
+            .corners()
+            .iter()
+            .map(|(x, y)| f64::sqrt(x * x + y * y))
+            .fold(0.0, f64::max);
+        Bounds::around(radius)
+    }
+
+    /// bounds for any scale between 0 and `factor`
+    pub fn scale_up_to(&self, factor: f64) -> Bounds {
+        if self.is_empty() {
+            return *self;
+        }
+        self.scale(factor).union(&Bounds::around(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn transformed_bounds() {
+        let bounds = Bounds::rectangle(20.0, 10.0);
+        assert_eq!(bounds, Bounds::new(-10.0, -5.0, 10.0, 5.0));
+        assert_eq!(
+            bounds.scale(2.0).translate(100.0, 0.0),
+            Bounds::new(80.0, -10.0, 120.0, 10.0)
+        );
+        let rotated = bounds.rotate(std::f64::consts::FRAC_PI_2);
+        assert!((rotated.max_x - 5.0).abs() < 1e-9);
+        assert!((rotated.max_y - 10.0).abs() < 1e-9);
+        assert_eq!(
+            bounds.scale_up_to(2.0).translate(30.0, 0.0),
+            Bounds::new(10.0, -10.0, 50.0, 10.0)
+        );
+        assert!(Bounds::empty().translate(1.0, 1.0).is_empty());
+        assert!(Bounds::empty().any_rotation().is_empty());
+        assert!(!bounds.intersects(&Bounds::empty()));
+        assert!(bounds.intersects(&Bounds::new(9.0, 4.0, 20.0, 20.0)));
+        assert!(!bounds.intersects(&Bounds::new(11.0, 4.0, 20.0, 20.0)));
+    }
+}
@@ -0,0 +1,362 @@
+//! variables and simple arithmetic expressions,
+//! which are evaluated when the structure is loaded.
+//!
+//! Every string which starts with a `$` is an expression,
+//! like `"$cell * 2"` or `"$($width - 10) / 3"`,
+//! other strings (like `"a$b.svg"`) are kept as they are.
+//! A string which is just a variable (like `"$color"`) is replaced
+//! by the value of the variable, which doesn't need to be a number.
+//! A string starting with `$$` is kept, without the first `$`.
+
+use crate::validate::{Problem, Severity};
+use serde_json::value::{Map, Value};
+use std::collections::HashMap;
+
+/// evaluate the variables of a file (and all expressions in it),
+/// except the `templates`, which are evaluated when they are used.
+pub fn resolve_file(value: Value) -> Result<Value, Problem> {
+    let fields = match value {
+        Value::Object(fields) => fields,
+        other => return Ok(other),
+    };
+    let variables = match fields.get("vars") {
+        Some(Value::Object(definitions)) => resolve_variables(definitions)?,
+        Some(_) => return Err(problem("vars", "vars must be an object".to_string())),
+        None => HashMap::new(),
+    };
+    let resolved = fields
+        .iter()
+        .map(|(key, field)| match key.as_str() {
+            "templates" => Ok((key.clone(), field.clone())),
+            "vars" => Ok((
+                key.clone(),
+                Value::Object(variables.clone().into_iter().collect()),
+            )),
+            _ => Ok((
+                key.clone(),
+                resolve_with(field, key, &mut |name| lookup(&variables, name))?,
+            )),
+        })
+        .collect::<Result<Map<String, Value>, Problem>>()?;
+    Ok(Value::Object(resolved))
+}
+
+/// evaluate variables, which can use other variables
+pub fn resolve_variables(
+    definitions: &Map<String, Value>,
+) -> Result<HashMap<String, Value>, Problem> {
+    let mut resolved = HashMap::new();
+    for name in definitions.keys() {
+        variable(name, definitions, &mut resolved, &mut Vec::new())?;
+    }
+    Ok(resolved)
+}
+
+fn variable(
+    name: &str,
+    definitions: &Map<String, Value>,
+    resolved: &mut HashMap<String, Value>,
+    resolving: &mut Vec<String>,
+) -> Result<Value, Problem> {
+    let path = format!("vars.{}", name);
+    if let Some(value) = resolved.get(name) {
+        return Ok(value.clone());
+    }
+    if resolving.iter().any(|other| other == name) {
+        resolving.push(name.to_string());
+        let message = format!("variables use each other : {}", resolving.join(" -> "));
+        return Err(problem(&path, message));
+    }
+    let definition = definitions
+        .get(name)
+        .ok_or_else(|| problem(&path, format!("unknown variable \"{}\"", name)))?;
+    resolving.push(name.to_string());
+    // the error of a variable used by this one is only reported with its message
+    let value = resolve_with(definition, &path, &mut |other| {
+        variable(other, definitions, resolved, resolving).map_err(|problem| problem.message)
+    })?;
+    resolving.pop();
+    resolved.insert(name.to_string(), value.clone());
+    Ok(value)
+}
+
+/// evaluate all expressions in the value
+pub fn resolve(value: &Value, variables: &HashMap<String, Value>) -> Result<Value, String> {
+    resolve_with(value, "", &mut |name| lookup(variables, name)).map_err(|problem| {
+        if problem.path.is_empty() {
+            problem.message
+        } else {
+            format!("{} : {}", problem.path, problem.message)
+        }
+    })
+}
+
+type Lookup<'a> = dyn FnMut(&str) -> Result<Value, String> + 'a;
+
+fn lookup(variables: &HashMap<String, Value>, name: &str) -> Result<Value, String> {
+    variables
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("unknown variable \"{}\"", name))
+}
+
+fn problem(path: &str, message: String) -> Problem {
+    Problem {
+        severity: Severity::Error,
+        path: path.to_string(),
+        message,
+    }
+}
+
+/// `path` is the path of the value, used for errors
+fn resolve_with(value: &Value, path: &str, lookup: &mut Lookup) -> Result<Value, Problem> {
+    match value {
+        Value::String(text) if text.trim_start().starts_with("$$") => {
+            Ok(Value::String(text.replacen("$$", "$", 1)))
+        }
+        Value::String(text) if text.trim_start().starts_with('$') => {
+            let name = text.trim().trim_start_matches('$');
+            let result = if !name.is_empty() && name.chars().all(is_name) {
+                lookup(name)
+            } else {
+                evaluate_with(text, lookup).and_then(|result| {
+                    number(result).ok_or_else(|| format!("\"{}\" is not a number", text))
+                })
+            };
+            result.map_err(|message| problem(path, message))
+        }
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| resolve_with(item, &format!("{}[{}]", path, index), lookup))
+            .collect::<Result<Vec<Value>, Problem>>()
+            .map(Value::Array),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, item)| {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                Ok((key.clone(), resolve_with(item, &path, lookup)?))
+            })
+            .collect::<Result<Map<String, Value>, Problem>>()
+            .map(Value::Object),
+        _ => Ok(value.clone()),
+    }
+}
+
+/// whole numbers become integers, so they can be used for integer fields
+fn number(value: f64) -> Option<Value> {
+    if value.fract() == 0.0 && value.abs() < 9_007_199_254_740_992.0 {
+        Some(Value::from(value as i64))
+    } else {
+        serde_json::Number::from_f64(value).map(Value::Number)
+    }
+}
+
+fn is_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// evaluate an arithmetic expression, using `+ - * / %`, parentheses and variables
+pub fn evaluate(expression: &str, variables: &HashMap<String, Value>) -> Result<f64, String> {
+    evaluate_with(expression, &mut |name| lookup(variables, name))
+}
+
+fn evaluate_with(expression: &str, lookup: &mut Lookup) -> Result<f64, String> {
+    let mut parser = Parser {
+        chars: expression.chars().collect(),
+        position: 0,
+        lookup,
+    };
+    let result = parser.sum()?;
+    parser.skip_whitespace();
+    if parser.position < parser.chars.len() {
+        return Err(parser.error("unexpected character"));
+    }
+    Ok(result)
+}
+
+struct Parser<'a, 'b> {
+    chars: Vec<char>,
+    position: usize,
+    lookup: &'a mut Lookup<'b>,
+}
+
+impl Parser<'_, '_> {
+    fn error(&self, message: &str) -> String {
+        format!(
+            "invalid expression \"{}\" at position {}: {}",
+            self.chars.iter().collect::<String>(),
+            self.position,
+            message
+        )
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.position < self.chars.len() && self.chars[self.position].is_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    /// the next (non white space) character
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.position).cloned()
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut result = self.product()?;
+        loop {
+            match self.peek() {
+                Some('+') => {
+                    self.position += 1;
+                    result += self.product()?;
+                }
+                Some('-') => {
+                    self.position += 1;
+                    result -= self.product()?;
+                }
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut result = self.factor()?;
+        loop {
+            match self.peek() {
+                Some('*') => {
+                    self.position += 1;
+                    result *= self.factor()?;
+                }
+                Some('/') => {
+                    self.position += 1;
+                    result /= self.factor()?;
+                }
+                Some('%') => {
+                    self.position += 1;
+                    result %= self.factor()?;
+                }
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some('-') => {
+                self.position += 1;
+                Ok(-self.factor()?)
+            }
+            Some('(') => {
+                self.position += 1;
+                let result = self.sum()?;
+                if self.peek() != Some(')') {
+                    return Err(self.error("expected )"));
+                }
+                self.position += 1;
+                Ok(result)
+            }
+            // `$(` starts an expression with parentheses
+            Some('$') if self.chars.get(self.position + 1) == Some(&'(') => {
+                self.position += 1;
+                self.factor()
+            }
+            Some('$') => {
+                self.position += 1;
+                let start = self.position;
+                while self.position < self.chars.len() && is_name(self.chars[self.position]) {
+                    self.position += 1;
+                }
+                let name: String = self.chars[start..self.position].iter().collect();
+                if name.is_empty() {
+                    return Err(self.error("expected a variable name"));
+                }
+                let value = (self.lookup)(&name)?;
+                value
+                    .as_f64()
+                    .ok_or_else(|| format!("variable \"{}\" is not a number", name))
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.position;
+                while self.position < self.chars.len()
+                    && (self.chars[self.position].is_ascii_digit()
+                        || self.chars[self.position] == '.')
+                {
+                    self.position += 1;
+                }
+                let number: String = self.chars[start..self.position].iter().collect();
+                number.parse().map_err(|_| self.error("invalid number"))
+            }
+            _ => Err(self.error("expected a number, a variable or (")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn evaluate_arithmetic() {
+        let mut variables = HashMap::new();
+        variables.insert("cell".to_string(), json!(50));
+        assert_eq!(evaluate("$cell * 2 + 1", &variables), Ok(101.0));
+        assert_eq!(evaluate("($cell - 10) / -4", &variables), Ok(-10.0));
+        assert_eq!(evaluate("7 % 4 * 2", &variables), Ok(6.0));
+        assert!(evaluate("$size", &variables).is_err());
+        assert!(evaluate("2 +", &variables).is_err());
+        assert!(evaluate("(2", &variables).is_err());
+        assert_eq!(evaluate("$($cell - 10) / 4", &variables), Ok(10.0));
+    }
+
+    #[test]
+    fn resolve_variables_of_a_file() {
+        let file = json!({
+            "width": "$cell * 4",
+            "vars": {"cell": 25, "half": "$cell / 2", "color": "background"},
+            "objects": {"dot": {"type": "circle", "radius": "$half", "color": "$color"}},
+            "templates": {"petal": {"object": {"radius": "$radius"}}}
+        });
+        let resolved = resolve_file(file).unwrap();
+        assert_eq!(resolved["width"], json!(100));
+        assert_eq!(resolved["vars"]["half"], json!(12.5));
+        assert_eq!(resolved["objects"]["dot"]["radius"], json!(12.5));
+        assert_eq!(resolved["objects"]["dot"]["color"], json!("background"));
+        assert_eq!(
+            resolved["templates"]["petal"]["object"]["radius"],
+            json!("$radius")
+        );
+
+        let cycle = json!({"vars": {"a": "$b + 1", "b": "$a"}});
+        let problem = resolve_file(cycle).unwrap_err();
+        assert_eq!(problem.path, "vars.a");
+        assert!(problem.message.contains("use each other"));
+    }
+
+    #[test]
+    fn only_strings_starting_with_a_dollar_are_expressions() {
+        let file = json!({
+            "vars": {"cell": 25},
+            "objects": {
+                "logo": {"type": "svg", "file": "a$b.svg"},
+                "price": {"type": "svg", "file": "$$5.svg"},
+                "dot": {"type": "circle", "radius": "$($cell + 5) / 2"}
+            }
+        });
+        let resolved = resolve_file(file).unwrap();
+        assert_eq!(resolved["objects"]["logo"]["file"], json!("a$b.svg"));
+        assert_eq!(resolved["objects"]["price"]["file"], json!("$5.svg"));
+        assert_eq!(resolved["objects"]["dot"]["radius"], json!(15));
+
+        let file = json!({"objects": {"rows": {"points": [1, "$cell * 2"]}}});
+        let problem = resolve_file(file).unwrap_err();
+        assert_eq!(problem.path, "objects.rows.points[1]");
+        assert_eq!(problem.message, "unknown variable \"cell\"");
+    }
+}
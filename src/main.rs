@@ -6,28 +6,36 @@ use cairo::ImageSurface;
 
 use std::fs::File;
 
+pub mod bounds;
+pub mod expression;
 pub mod objects;
 pub mod palette;
+pub mod path;
 pub mod rendable;
+pub mod schema;
 pub mod structure;
+pub mod svg;
+pub mod template;
+pub mod validate;
 
-use crate::rendable::Rendable;
 use crate::structure::ImageContext;
 use crate::structure::Structure;
+use crate::validate::{Problem, Severity};
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use structopt::clap::{AppSettings, Error, ErrorKind};
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
-#[structopt(name = "basic")]
+#[structopt(name = "basic", settings = &[AppSettings::ArgsNegateSubcommands])]
 struct Opt {
     /// Output file (will be a png)
     #[structopt(short, long, parse(from_os_str))]
-    output: PathBuf,
+    output: Option<PathBuf>,
 
-    /// Input file (in json)
+    /// Input file (json, yaml, toml or jsonnet)
     #[structopt(name = "input.json", parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
 
     /// Optional : override width (default is 100)
     /// you can also set width in the input.json
@@ -48,35 +56,208 @@ struct Opt {
     /// you can also set line_size in the input.json
     #[structopt(long)]
     line_size: Option<f64>,
+
+    /// Optional : override min feature size (default is 0.3)
+    /// objects are not drawn when 100 units are smaller than this (in pixels).
+    /// you can also set min_feature_size in the input.json
+    #[structopt(long)]
+    min_feature_size: Option<f64>,
+
+    /// Optional : override seed (default is random)
+    /// use the same seed to create the same image again.
+    /// you can also set seed in the input.json
+    #[structopt(long)]
+    seed: Option<u64>,
+
+    #[structopt(flatten)]
+    variables: Variables,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+enum Command {
+    /// Check the input file for mistakes, without rendering it
+    Check {
+        /// Input file (json, yaml, toml or jsonnet)
+        #[structopt(name = "input.json", parse(from_os_str))]
+        input: PathBuf,
+
+        /// Optional : print the problems as json
+        #[structopt(long)]
+        json: bool,
+
+        /// Optional : override depth (default is 30)
+        /// of recursion allowed.
+        #[structopt(long)]
+        depth: Option<i32>,
+
+        #[structopt(flatten)]
+        variables: Variables,
+    },
+
+    /// Print the json schema of the input file, for editors
+    Schema,
+}
+
+/// variables for jsonnet input files
+#[derive(StructOpt)]
+struct Variables {
+    /// Optional : external variable for jsonnet input files
+    /// (use it with `std.extVar("name")`), like `--ext-str name=value`
+    #[structopt(long, parse(try_from_str = parse_variable), number_of_values = 1)]
+    ext_str: Vec<(String, String)>,
+
+    /// Optional : top level argument for jsonnet input files,
+    /// like `--tla-str name=value`
+    #[structopt(long, parse(try_from_str = parse_variable), number_of_values = 1)]
+    tla_str: Vec<(String, String)>,
+}
+
+/// parse `name=value`
+fn parse_variable(input: &str) -> Result<(String, String), String> {
+    let mut parts = input.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(name), Some(value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("expected name=value, got {}", input)),
+    }
+}
+
+/// load the input file, errors are returned as problem
+fn load(input: &Path, variables: &Variables) -> Result<Structure, Problem> {
+    let error = |message: String| Problem {
+        severity: Severity::Error,
+        path: String::new(),
+        message,
+    };
+    if !input.exists() {
+        return Err(error(format!("{}, does not exist", input.display())));
+    }
+    let path = input.to_str().unwrap();
+    let structure = match input.extension().and_then(|extension| extension.to_str()) {
+        Some("jsonnet") => {
+            Structure::load_from_jsonnet(path, &variables.ext_str, &variables.tla_str)
+        }
+        _ if !variables.ext_str.is_empty() || !variables.tla_str.is_empty() => {
+            return Err(error(
+                "--ext-str and --tla-str can only be used with jsonnet files".to_string(),
+            ))
+        }
+        _ => Structure::load_from_file(path),
+    };
+    structure.map_err(|loading| match loading.downcast::<Problem>() {
+        Ok(problem) => *problem,
+        Err(loading) => error(loading.to_string()),
+    })
+}
+
+/// load and validate the input file, without rendering it
+fn check(input: &Path, json: bool, depth: Option<i32>, variables: &Variables) {
+    let problems = match load(input, variables) {
+        Ok(mut structure) => {
+            if let Some(depth) = depth {
+                structure.depth = depth;
+            }
+            validate::validate(&structure)
+        }
+        Err(problem) => vec![problem],
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&problems).unwrap());
+    } else {
+        for problem in problems.iter() {
+            println!("{}", problem);
+        }
+        let errors = problems
+            .iter()
+            .filter(|problem| problem.severity == Severity::Error)
+            .count();
+        println!(
+            "{}, {} errors, {} warnings",
+            input.display(),
+            errors,
+            problems.len() - errors
+        );
+    }
+    if validate::has_errors(&problems) {
+        std::process::exit(1);
+    }
 }
 
 fn main() {
     // parse options
     let opt = Opt::from_args();
 
-    if !opt.input.exists() {
-        println!("{}, does not exist", opt.input.to_str().unwrap());
-        std::process::exit(1);
+    match &opt.command {
+        Some(Command::Check {
+            input,
+            json,
+            depth,
+            variables,
+        }) => {
+            check(input, *json, *depth, variables);
+            return;
+        }
+        Some(Command::Schema) => {
+            let schema = schema::schema();
+            println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+            return;
+        }
+        None => (),
     }
-    let structure = Structure::load_from_file(&opt.input.to_str().unwrap()).unwrap();
-    let image_context = ImageContext::new(&structure);
 
-    let width = match opt.width {
-        Some(width) => width,
-        None => structure.width,
-    };
-    let height = match opt.height {
-        Some(height) => height,
-        None => structure.height,
+    // without a subcommand both are required
+    let (input, output) = match (&opt.input, &opt.output) {
+        (Some(input), Some(output)) => (input, output),
+        _ => Error::with_description(
+            "an input file and an output file (--output) are required, or use a subcommand",
+            ErrorKind::MissingRequiredArgument,
+        )
+        .exit(),
     };
-    let line_size = match opt.line_size {
-        Some(line_size) => line_size,
-        None => structure.line_size,
-    };
-    let depth = match opt.depth {
-        Some(depth) => depth,
-        None => structure.depth,
+
+    let mut structure = match load(input, &opt.variables) {
+        Ok(structure) => structure,
+        Err(problem) => {
+            eprintln!("{}", problem);
+            std::process::exit(1);
+        }
     };
+    if opt.seed.is_some() {
+        structure.seed = opt.seed;
+    }
+    if let Some(depth) = opt.depth {
+        structure.depth = depth;
+    }
+    if let Some(min_feature_size) = opt.min_feature_size {
+        structure.min_feature_size = min_feature_size;
+    }
+    if let Some(width) = opt.width {
+        structure.width = width;
+    }
+    if let Some(height) = opt.height {
+        structure.height = height;
+    }
+    if let Some(line_size) = opt.line_size {
+        structure.line_size = line_size;
+    }
+
+    // only reported, the structure is rendered anyway (use `check` to fail on errors)
+    for problem in validate::validate(&structure) {
+        let warning = Problem {
+            severity: Severity::Warning,
+            ..problem
+        };
+        eprintln!("{}", warning);
+    }
+
+    let image_context = ImageContext::new(&structure);
+    eprintln!("seed {}", image_context.seed);
+
+    let width = structure.width;
+    let height = structure.height;
 
     let surface = ImageSurface::create(Format::Rgb24, width, height).expect("Can't create surface");
     let context = Context::new(&surface);
@@ -97,17 +278,25 @@ fn main() {
         f64::from(palette.fill_color.green),
         f64::from(palette.fill_color.blue),
     );
-    context.set_line_width(line_size);
+    context.set_line_width(structure.line_size);
 
     // center
     context.translate(f64::from(width) / 2.0, f64::from(height) / 2.0);
-    structure.render(&context, &image_context, depth);
+    structure.render(&context, &image_context);
+    eprintln!(
+        "culled {} objects, because they were too small",
+        image_context.culled()
+    );
+    eprintln!(
+        "skipped {} objects, because they were outside of the image",
+        image_context.off_canvas()
+    );
 
-    render_image(&opt.output.to_string_lossy(), &surface);
+    render_image(&output.to_string_lossy(), &surface);
 }
 
 fn render_image(path: &str, surface: &ImageSurface) {
-    let mut file = File::create(&path).expect("Couldn't create 'file.png'");
+    let mut file = File::create(path).expect("Couldn't create 'file.png'");
     match surface.write_to_png(&mut file) {
         Ok(_) => println!("{}, created", path),
         Err(_) => println!("Error create file.png"),
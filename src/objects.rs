@@ -10,11 +10,28 @@
 //!
 //!
 
+use crate::bounds::Bounds;
+use crate::path;
+use crate::path::{PathData, Segment};
 use crate::rendable::Rendable;
 use crate::structure::ImageContext;
 use crate::structure::Query;
+use crate::svg;
+use crate::validate::Ranges;
 use cairo::Context;
+use noise::{NoiseFn, Perlin};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use schemars::JsonSchema;
+use serde::de::{self, DeserializeOwned, Deserializer, SeqAccess, Visitor};
 use serde::{Deserialize, Serialize};
+use serde_json::value::Value;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
 
 /// helper function
 #[inline(always)]
@@ -22,9 +39,69 @@ fn degree_to_radian(degree: f64) -> f64 {
     degree * 0.017453293
 }
 
+/// how containers (like grid and sun) place each object in their cells.
+struct CellTransform<'a> {
+    scale: f64,
+    random_rotation: &'a [f64],
+    rotation_step: f64,
+    scale_jitter: f64,
+    position_jitter: f64,
+}
+
+impl<'a> CellTransform<'a> {
+    /// place the cell `index` of a container.
+    /// applies the position jitter, the rotation and the (jittered) scale.
+    /// returns false if the cell is scaled to nothing and should not be drawn.
+    fn apply(&self, context: &Context, image_context: &ImageContext, index: i32) -> bool {
+        let mut rng = image_context.rng();
+
+        if self.position_jitter > 0.0 {
+            context.translate(
+                rng.gen_range(-self.position_jitter, self.position_jitter),
+                rng.gen_range(-self.position_jitter, self.position_jitter),
+            );
+        }
+
+        let mut angle = f64::from(index) * self.rotation_step;
+        if let Some(rotation) = self.random_rotation.choose(&mut *rng) {
+            angle += rotation;
+        }
+        context.rotate(degree_to_radian(angle));
+
+        let scale = if self.scale_jitter > 0.0 {
+            self.scale + rng.gen_range(-self.scale_jitter, self.scale_jitter)
+        } else {
+            self.scale
+        };
+        if scale <= 0.0 {
+            return false;
+        }
+        context.scale(0.01 * scale, 0.01 * scale);
+        true
+    }
+
+    /// estimate the bounds of an object placed by `apply`.
+    fn bounds(&self, child: Bounds) -> Bounds {
+        if self.scale + self.scale_jitter.max(0.0) <= 0.0 {
+            return Bounds::empty();
+        }
+        let rotated = if self.random_rotation.is_empty() && self.rotation_step == 0.0 {
+            child
+        } else {
+            child.any_rotation()
+        };
+        let scaled = if self.scale_jitter > 0.0 {
+            rotated.scale_up_to(0.01 * (self.scale + self.scale_jitter))
+        } else {
+            rotated.scale(0.01 * self.scale)
+        };
+        scaled.expand(self.position_jitter)
+    }
+}
+
 /// Configures the color to use from the palette to draw.
 /// (default is `fill`)
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
 pub enum Color {
     /// Use the background color from the palette to draw.
     #[serde(rename = "background")]
@@ -32,18 +109,13 @@ pub enum Color {
 
     /// Use the `fill` color from the palette to draw.
     #[serde(rename = "fill")]
+    #[default]
     Fill,
 }
 
-impl Color {
-    pub fn default() -> Color {
-        Color::Fill
-    }
-}
-
 /// All object types which can be used
 /// in your `input.json`.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum Object {
     /// A container to draw multiple objects in row.
@@ -61,6 +133,18 @@ pub enum Object {
     #[serde(rename = "grid")]
     Grid(Grid),
 
+    /// A container to draw objects along a path.
+    #[serde(rename = "along_path")]
+    AlongPath(AlongPath),
+
+    /// A container to draw objects on a spiral.
+    #[serde(rename = "spiral")]
+    Spiral(Spiral),
+
+    /// A container to split an area in rectangles and draw objects in them.
+    #[serde(rename = "subdivide")]
+    Subdivide(Subdivide),
+
     /// draw an unfilled ring (for filling use `circle`)
     #[serde(rename = "ring")]
     Ring(Ring),
@@ -69,6 +153,18 @@ pub enum Object {
     #[serde(rename = "circle")]
     Circle(Circle),
 
+    /// draw a part of a ring
+    #[serde(rename = "arc")]
+    Arc(Arc),
+
+    /// draw a filled part of a circle
+    #[serde(rename = "pie")]
+    Pie(Pie),
+
+    /// draw an ellipse
+    #[serde(rename = "ellipse")]
+    Ellipse(Ellipse),
+
     /// draw a path an fill it
     #[serde(rename = "icon")]
     Icon(Icon),
@@ -76,20 +172,291 @@ pub enum Object {
     /// draw a path
     #[serde(rename = "line")]
     Line(Line),
+
+    /// draw the shapes of an svg file
+    #[serde(rename = "svg")]
+    Svg(Svg),
+
+    /// draw a png image
+    #[serde(rename = "image")]
+    Image(Image),
+
+    /// draw a path created by a lindenmayer system
+    #[serde(rename = "lsystem")]
+    LSystem(LSystem),
+
+    /// draw the cells of a voronoi diagram
+    #[serde(rename = "voronoi")]
+    Voronoi(Voronoi),
+
+    /// draw lines following a noise field
+    #[serde(rename = "flow_field")]
+    FlowField(FlowField),
+
+    /// draw a random path
+    #[serde(rename = "random_walk")]
+    RandomWalk(RandomWalk),
 }
 
 impl Object {
+    /// Objects are internally tagged, so their errors don't have the path
+    /// to the field. Reading the object again as its type finds the field.
+    pub fn field_error(value: &Value) -> Option<serde_path_to_error::Error<serde_json::Error>> {
+        fn error<T: DeserializeOwned>(
+            value: &Value,
+        ) -> Option<serde_path_to_error::Error<serde_json::Error>> {
+            serde_path_to_error::deserialize::<_, T>(value).err()
+        }
+        match value.get("type")?.as_str()? {
+            "sequence" | "seq" => error::<Sequence>(value),
+            "sun" => error::<Sun>(value),
+            "grid" => error::<Grid>(value),
+            "along_path" => error::<AlongPath>(value),
+            "spiral" => error::<Spiral>(value),
+            "subdivide" => error::<Subdivide>(value),
+            "ring" => error::<Ring>(value),
+            "circle" => error::<Circle>(value),
+            "arc" => error::<Arc>(value),
+            "pie" => error::<Pie>(value),
+            "ellipse" => error::<Ellipse>(value),
+            "icon" => error::<Icon>(value),
+            "line" => error::<Line>(value),
+            "svg" => error::<Svg>(value),
+            "image" => error::<Image>(value),
+            "lsystem" => error::<LSystem>(value),
+            "voronoi" => error::<Voronoi>(value),
+            "flow_field" => error::<FlowField>(value),
+            "random_walk" => error::<RandomWalk>(value),
+            _ => None,
+        }
+    }
+
     /// extracts tag value from Objects
     pub fn get_tags(&self) -> &Vec<String> {
         match &self {
             Object::Circle(element) => &element.tags,
+            Object::Arc(element) => &element.tags,
+            Object::Pie(element) => &element.tags,
+            Object::Ellipse(element) => &element.tags,
             Object::Grid(element) => &element.tags,
             Object::Icon(element) => &element.tags,
             Object::Line(element) => &element.tags,
+            Object::Svg(element) => &element.tags,
+            Object::Image(element) => &element.tags,
             Object::Ring(element) => &element.tags,
             Object::Sequence(element) => &element.tags,
             Object::Seq(element) => &element.tags,
             Object::Sun(element) => &element.tags,
+            Object::AlongPath(element) => &element.tags,
+            Object::LSystem(element) => &element.tags,
+            Object::Spiral(element) => &element.tags,
+            Object::Subdivide(element) => &element.tags,
+            Object::Voronoi(element) => &element.tags,
+            Object::FlowField(element) => &element.tags,
+            Object::RandomWalk(element) => &element.tags,
+        }
+    }
+
+    /// all queries of the object (and all objects it contains)
+    pub fn queries_mut(&mut self) -> Vec<&mut Query> {
+        match self {
+            Object::Grid(element) => vec![&mut element.query],
+            Object::Sun(element) => vec![&mut element.query],
+            Object::AlongPath(element) => vec![&mut element.query],
+            Object::Spiral(element) => vec![&mut element.query],
+            Object::Subdivide(element) => vec![&mut element.query],
+            Object::LSystem(element) => element.queries.values_mut().collect(),
+            Object::Voronoi(element) => element.query.iter_mut().collect(),
+            Object::Sequence(element) | Object::Seq(element) => element
+                .objects
+                .iter_mut()
+                .flat_map(|object| object.queries_mut())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// all queries of the object (and all objects it contains),
+    /// with their path in the object (like `query` or `objects[1].query`)
+    pub fn queries(&self) -> Vec<(String, &Query)> {
+        self.scaled_queries()
+            .into_iter()
+            .map(|(path, query, _)| (path, query))
+            .collect()
+    }
+
+    /// like `queries`, but also with the biggest scale the objects found
+    /// by the query are drawn with (1.0 is the size of the object itself).
+    pub fn scaled_queries(&self) -> Vec<(String, &Query, f64)> {
+        // the biggest scale of all levels of recursion
+        let by_depth = |scale_by_depth: &[f64]| {
+            if scale_by_depth.is_empty() {
+                1.0
+            } else {
+                0.01 * scale_by_depth.iter().cloned().fold(f64::MIN, f64::max)
+            }
+        };
+        let query = |query, scale: f64| vec![("query".to_string(), query, scale)];
+        match self {
+            Object::Grid(element) => query(
+                &element.query,
+                0.01 * (element.scale + element.scale_jitter.max(0.0))
+                    * by_depth(&element.scale_by_depth),
+            ),
+            Object::Sun(element) => query(
+                &element.query,
+                0.01 * (element.scale + element.scale_jitter.max(0.0))
+                    * by_depth(&element.scale_by_depth),
+            ),
+            Object::AlongPath(element) => query(
+                &element.query,
+                0.01 * element.scale * by_depth(&element.scale_by_depth),
+            ),
+            Object::Spiral(element) => query(
+                &element.query,
+                0.01 * f64::max(
+                    element.scale,
+                    element.scale + f64::from(element.count - 1) * element.scale_step,
+                ) * by_depth(&element.scale_by_depth),
+            ),
+            Object::Subdivide(element) => query(
+                &element.query,
+                0.0001
+                    * element.scale
+                    * by_depth(&element.scale_by_depth)
+                    * f64::max(element.width.abs(), element.height.abs()),
+            ),
+            Object::LSystem(element) => {
+                let scale = 0.01 * element.scale * by_depth(&element.scale_by_depth);
+                let mut queries: Vec<(String, &Query, f64)> = element
+                    .queries
+                    .iter()
+                    .map(|(symbol, query)| (format!("queries.{}", symbol), query, scale))
+                    .collect();
+                queries.sort_by(|a, b| a.0.cmp(&b.0));
+                queries
+            }
+            Object::Voronoi(element) => element
+                .query
+                .iter()
+                .map(|query| {
+                    let scale = 0.01 * element.scale * by_depth(&element.scale_by_depth);
+                    ("query".to_string(), query, scale)
+                })
+                .collect(),
+            Object::Sequence(element) | Object::Seq(element) => {
+                let scale = 0.01 * element.scale * by_depth(&element.scale_by_depth);
+                element
+                    .objects
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(index, object)| {
+                        object
+                            .scaled_queries()
+                            .into_iter()
+                            .map(move |(path, query, inner)| {
+                                (format!("objects[{}].{}", index, path), query, scale * inner)
+                            })
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// check the numbers of the object (and all objects it contains) are in their range
+    pub fn check_ranges(&self, ranges: &mut Ranges) {
+        match self {
+            Object::Sequence(element) | Object::Seq(element) => {
+                ranges.positive("scale", element.scale);
+                for (index, object) in element.objects.iter().enumerate() {
+                    object.check_ranges(&mut ranges.nested(&format!("objects[{}]", index)));
+                }
+            }
+            Object::Grid(element) => {
+                ranges.at_least("rows", element.rows, 1);
+                ranges.at_least("columns", element.columns, 1);
+                ranges.positive("scale", element.scale);
+                ranges.at_least("scale_jitter", element.scale_jitter, 0.0);
+                ranges.at_least("position_jitter", element.position_jitter, 0.0);
+            }
+            Object::Sun(element) => {
+                ranges.at_least("segments", element.segments, 1);
+                ranges.positive("scale", element.scale);
+                ranges.at_least("scale_jitter", element.scale_jitter, 0.0);
+                ranges.at_least("position_jitter", element.position_jitter, 0.0);
+            }
+            Object::AlongPath(element) => {
+                ranges.at_least("count", element.count, 1);
+                ranges.positive("scale", element.scale);
+            }
+            Object::Spiral(element) => {
+                ranges.at_least("count", element.count, 1);
+                ranges.at_least("turns", element.turns, 0.0);
+                ranges.at_least("radius", element.radius, 0.0);
+                ranges.positive("scale", element.scale);
+            }
+            Object::Subdivide(element) => {
+                ranges.positive("width", element.width);
+                ranges.positive("height", element.height);
+                ranges.at_least("levels", element.levels, 0);
+                ranges.between("split_chance", element.split_chance, 0.0, 100.0);
+                ranges.between("min_ratio", element.min_ratio, 0.0, 100.0);
+                ranges.between("max_ratio", element.max_ratio, element.min_ratio, 100.0);
+                ranges.positive("scale", element.scale);
+            }
+            Object::Ring(element) => ranges.at_least("radius", element.radius, 0.0),
+            Object::Circle(element) => ranges.at_least("radius", element.radius, 0.0),
+            Object::Arc(element) => ranges.at_least("radius", element.radius, 0.0),
+            Object::Pie(element) => ranges.at_least("radius", element.radius, 0.0),
+            Object::Ellipse(element) => {
+                ranges.positive("rx", element.rx);
+                ranges.positive("ry", element.ry);
+            }
+            Object::Icon(_) | Object::Line(_) => (),
+            Object::Svg(element) => ranges.positive("size", element.size),
+            Object::Image(element) => {
+                ranges.positive("width", element.width);
+                ranges.positive("height", element.height);
+                ranges.between("opacity", element.opacity, 0.0, 100.0);
+            }
+            Object::LSystem(element) => {
+                ranges.at_least("iterations", element.iterations, 0);
+                ranges.positive("scale", element.scale);
+            }
+            Object::Voronoi(element) => {
+                ranges.at_least("points", element.points, 1);
+                ranges.positive("width", element.width);
+                ranges.positive("height", element.height);
+                ranges.positive("scale", element.scale);
+            }
+            Object::FlowField(element) => {
+                ranges.at_least("lines", element.lines, 1);
+                ranges.at_least("steps", element.steps, 1);
+                ranges.positive("step", element.step);
+                ranges.positive("width", element.width);
+                ranges.positive("height", element.height);
+            }
+            Object::RandomWalk(element) => {
+                ranges.at_least("steps", element.steps, 1);
+                ranges.positive("step", element.step);
+            }
+        }
+    }
+
+    /// load the files used by the object (and all objects it contains),
+    /// relative file names are relative to `directory`.
+    pub fn load_files(&mut self, directory: &std::path::Path) -> Result<(), Box<dyn Error>> {
+        match self {
+            Object::Svg(element) => element.load(directory),
+            Object::Image(element) => element.load(directory),
+            Object::Sequence(element) | Object::Seq(element) => {
+                for object in element.objects.iter_mut() {
+                    object.load_files(directory)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
         }
     }
 }
@@ -109,7 +476,7 @@ impl Object {
 ///  ],
 /// }
 /// ```
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct Sequence {
     /// list of objects to be drawn
     objects: Vec<Object>,
@@ -122,6 +489,11 @@ pub struct Sequence {
     #[serde(default = "Sequence::default_scale")]
     pub scale: f64,
 
+    /// rescale for each level of recursion (in percent like `scale`),
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub scale_by_depth: Vec<f64>,
+
     /// x coordinate of center (default is 0)
     #[serde(default)]
     pub x: f64,
@@ -147,29 +519,40 @@ impl Rendable for Sequence {
 
         context.translate(self.x, self.y);
         context.rotate(degree_to_radian(self.angle));
-        context.scale(0.01 * self.scale, 0.01 * self.scale);
+        let scale = self.scale * 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth);
+        context.scale(0.01 * scale, 0.01 * scale);
 
         // stop rendering when scale is to small
-        let (x0, y0) = context.user_to_device_distance(100.0, 100.0);
-        let (x1, y1) = context.user_to_device_distance(0.0, 0.0);
-        if f64::sqrt((x1 - x0).powi(2) + (y1 - y0).powi(2)) < 0.3 {
-            return ();
+        if image_context.is_too_small(context) {
+            context.restore();
+            return;
         }
 
         for object in self.objects.iter() {
-            match object {
-                Object::Circle(element) => element.render(&context, image_context, depth),
-                Object::Grid(element) => element.render(&context, image_context, depth),
-                Object::Icon(element) => element.render(&context, image_context, depth),
-                Object::Line(element) => element.render(&context, image_context, depth),
-                Object::Ring(element) => element.render(&context, image_context, depth),
-                Object::Sequence(element) => element.render(&context, image_context, depth),
-                Object::Seq(element) => element.render(&context, image_context, depth),
-                Object::Sun(element) => element.render(&context, image_context, depth),
+            if let Some(rendable) = ImageContext::object_to_rendable_box(object) {
+                if image_context.is_visible(context, *rendable, depth) {
+                    rendable.render(context, image_context, depth);
+                }
             }
         }
         context.restore();
     }
+
+    fn bounds(&self, image_context: &ImageContext, depth: i32) -> Option<Bounds> {
+        let mut bounds = Bounds::empty();
+        for object in self.objects.iter() {
+            if let Some(rendable) = ImageContext::object_to_rendable_box(object) {
+                bounds = bounds.union(&image_context.bounds_of(*rendable, depth)?);
+            }
+        }
+        let scale = self.scale * 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth);
+        Some(
+            bounds
+                .scale(0.01 * scale)
+                .rotate(degree_to_radian(self.angle))
+                .translate(self.x, self.y),
+        )
+    }
 }
 
 /// A container to draw multiple objects in a grid.
@@ -194,7 +577,7 @@ impl Rendable for Sequence {
 /// }
 /// ```
 ///
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct Grid {
     /// number of rows
     #[serde(default = "Grid::default_rows")]
@@ -223,6 +606,30 @@ pub struct Grid {
     #[serde(default = "Grid::default_scale")]
     pub scale: f64,
 
+    /// rescale for each level of recursion (in percent like `scale`),
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub scale_by_depth: Vec<f64>,
+
+    /// list of angles (in degree) one is randomly chosen from
+    /// to rotate each placed object (default is no rotation)
+    #[serde(default)]
+    pub random_rotation: Vec<f64>,
+
+    /// angle (in degree) every placed object is rotated
+    /// more than the previous one (default is 0)
+    #[serde(default)]
+    pub rotation_step: f64,
+
+    /// maximal random change of `scale` for each placed object (default is 0)
+    #[serde(default)]
+    pub scale_jitter: f64,
+
+    /// maximal random displacement in x and y direction
+    /// of each placed object (default is 0)
+    #[serde(default)]
+    pub position_jitter: f64,
+
     /// x coordinate of center (default is 0)
     #[serde(default)]
     pub x: f64,
@@ -240,6 +647,16 @@ impl Grid {
     fn default_scale() -> f64 {
         100.0
     }
+    /// how each object is placed (at the level of recursion of `depth`)
+    fn cell_transform(&self, image_context: &ImageContext, depth: i32) -> CellTransform<'_> {
+        CellTransform {
+            scale: self.scale * 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth),
+            random_rotation: &self.random_rotation,
+            rotation_step: self.rotation_step,
+            scale_jitter: self.scale_jitter,
+            position_jitter: self.position_jitter,
+        }
+    }
     fn default_width() -> f64 {
         100.0
     }
@@ -256,12 +673,11 @@ impl Grid {
 
 impl Rendable for Grid {
     fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
-        // stop rendering when scale is to small
-        let (x0, y0) = context.user_to_device_distance(100.0, 100.0);
-        let (x1, y1) = context.user_to_device_distance(0.0, 0.0);
-        if f64::sqrt((x1 - x0).powi(2) + (y1 - y0).powi(2)) < 0.3 {
-            return ();
-        }
+        // stop rendering when scale is to small (or only draw the fallback)
+        let depth = match image_context.depth_for_scale(context, &self.query, depth) {
+            Some(depth) => depth,
+            None => return,
+        };
 
         context.save();
         context.translate(self.x, self.y);
@@ -273,6 +689,7 @@ impl Rendable for Grid {
         let start_x = (total_width / 2.0) - (self.width / 2.0);
         let start_y = (total_height / 2.0) - (self.height / 2.0);
 
+        let cell_transform = self.cell_transform(image_context, depth);
         let mut query_result = image_context.get_element_from_query(&self.query, depth);
         for x in (std::ops::Range {
             start: 0,
@@ -282,15 +699,17 @@ impl Rendable for Grid {
                 start: 0,
                 end: self.rows,
             }) {
-                let rendable = query_result.next();
-                if rendable.is_some() {
+                if let Some(rendable) = query_result.next() {
                     context.save();
                     context.translate(
                         f64::from(x) * self.width - start_x,
                         f64::from(y) * self.height - start_y,
                     );
-                    context.scale(0.01 * self.scale, 0.01 * self.scale);
-                    rendable.unwrap().render(&context, image_context, depth - 1);
+                    if cell_transform.apply(context, image_context, x * self.rows + y)
+                        && image_context.is_visible(context, *rendable, depth - 1)
+                    {
+                        rendable.render(context, image_context, depth - 1);
+                    }
                     context.restore();
                 }
             }
@@ -298,6 +717,25 @@ impl Rendable for Grid {
 
         context.restore();
     }
+
+    fn bounds(&self, image_context: &ImageContext, depth: i32) -> Option<Bounds> {
+        if self.rows < 1 || self.columns < 1 {
+            return Some(Bounds::empty());
+        }
+        // the cells in the corners are the outer most cells
+        let half_width = self.width * f64::from(self.columns - 1) / 2.0;
+        let half_height = self.height * f64::from(self.rows - 1) / 2.0;
+        let bounds = image_context.query_bounds(&self.query, depth, |child, depth| {
+            let cell = self.cell_transform(image_context, depth).bounds(child);
+            cell.translate(-half_width, -half_height)
+                .union(&cell.translate(half_width, half_height))
+        })?;
+        Some(
+            bounds
+                .rotate(degree_to_radian(self.angle))
+                .translate(self.x, self.y),
+        )
+    }
 }
 
 /// A container to draw there objects in a circle.
@@ -312,7 +750,7 @@ impl Rendable for Grid {
 ///  "query": {"by_tag":["arrows"]}
 /// }
 /// ```
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct Sun {
     /// the query used to find the object which should be placed.
     pub query: Query,
@@ -335,6 +773,30 @@ pub struct Sun {
     #[serde(default = "Sun::default_scale")]
     pub scale: f64,
 
+    /// rescale for each level of recursion (in percent like `scale`),
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub scale_by_depth: Vec<f64>,
+
+    /// list of angles (in degree) one is randomly chosen from
+    /// to rotate each placed object (default is no rotation)
+    #[serde(default)]
+    pub random_rotation: Vec<f64>,
+
+    /// angle (in degree) every placed object is rotated
+    /// more than the previous one (default is 0)
+    #[serde(default)]
+    pub rotation_step: f64,
+
+    /// maximal random change of `scale` for each placed object (default is 0)
+    #[serde(default)]
+    pub scale_jitter: f64,
+
+    /// maximal random displacement in x and y direction
+    /// of each placed object (default is 0)
+    #[serde(default)]
+    pub position_jitter: f64,
+
     /// x coordinate of center (default is 0)
     #[serde(default)]
     pub x: f64,
@@ -352,6 +814,16 @@ impl Sun {
     fn default_scale() -> f64 {
         100.0
     }
+    /// how each object is placed (at the level of recursion of `depth`)
+    fn cell_transform(&self, image_context: &ImageContext, depth: i32) -> CellTransform<'_> {
+        CellTransform {
+            scale: self.scale * 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth),
+            random_rotation: &self.random_rotation,
+            rotation_step: self.rotation_step,
+            scale_jitter: self.scale_jitter,
+            position_jitter: self.position_jitter,
+        }
+    }
     fn default_radius() -> f64 {
         100.0
     }
@@ -367,15 +839,18 @@ impl Rendable for Sun {
         context.translate(self.x, self.y);
         context.rotate(degree_to_radian(self.angle));
 
-        // stop rendering when scale is to small
-        let (x0, y0) = context.user_to_device_distance(100.0, 100.0);
-        let (x1, y1) = context.user_to_device_distance(0.0, 0.0);
-        if f64::sqrt((x1 - x0).powi(2) + (y1 - y0).powi(2)) < 0.3 {
-            return ();
-        }
+        // stop rendering when scale is to small (or only draw the fallback)
+        let depth = match image_context.depth_for_scale(context, &self.query, depth) {
+            Some(depth) => depth,
+            None => {
+                context.restore();
+                return;
+            }
+        };
 
         let segment_rotation_factor = (2.0 * std::f64::consts::PI) / f64::from(self.segments);
 
+        let cell_transform = self.cell_transform(image_context, depth);
         let mut query_result = image_context.get_element_from_query(&self.query, depth);
 
         for segment in (std::ops::Range {
@@ -387,11 +862,13 @@ impl Rendable for Sun {
             context.rotate(f64::from(segment) * segment_rotation_factor);
             context.translate(self.radius, 0.0);
             context.rotate(degree_to_radian(90.0));
-            context.scale(0.01 * self.scale, 0.01 * self.scale);
 
-            let rendable = query_result.next();
-            if rendable.is_some() {
-                rendable.unwrap().render(&context, image_context, depth - 1);
+            if let Some(rendable) = query_result.next() {
+                if cell_transform.apply(context, image_context, segment)
+                    && image_context.is_visible(context, *rendable, depth - 1)
+                {
+                    rendable.render(context, image_context, depth - 1);
+                }
             }
 
             context.restore();
@@ -399,258 +876,2369 @@ impl Rendable for Sun {
 
         context.restore();
     }
-}
-
-#[derive(Serialize, Deserialize)]
-pub struct Point {
-    #[serde(default)]
-    pub x: f64,
-    #[serde(default)]
-    pub y: f64,
-}
 
-impl Default for Point {
-    fn default() -> Self {
-        Point { x: 0.0, y: 0.0 }
+    fn bounds(&self, image_context: &ImageContext, depth: i32) -> Option<Bounds> {
+        let segment_rotation_factor = (2.0 * std::f64::consts::PI) / f64::from(self.segments);
+        let bounds = image_context.query_bounds(&self.query, depth, |child, depth| {
+            let cell = self
+                .cell_transform(image_context, depth)
+                .bounds(child)
+                .rotate(degree_to_radian(90.0))
+                .translate(self.radius, 0.0);
+            (0..self.segments).fold(Bounds::empty(), |bounds, segment| {
+                bounds.union(&cell.rotate(f64::from(segment) * segment_rotation_factor))
+            })
+        })?;
+        Some(
+            bounds
+                .rotate(degree_to_radian(self.angle))
+                .translate(self.x, self.y),
+        )
     }
 }
 
-/// draw a ring (without filling)
+/// A container to draw objects along a path.
+/// The objects are placed in even distances
+/// measured along the path.
+/// Useful to draw borders, chains or vines.
 ///
-/// #Example
+/// # Example
 ///
 /// ```json
 /// {
-///  "type": "ring",
-///  "radius":50,
+///  "type": "along_path",
+///  "count": 20,
+///  "rotate": true,
+///  "query": {"by_name":"leaf"},
+///  "path":[
+///  { x: -100, y: 0},
+///  { x: 100, y: 0, sa: {x: 0, y:-100}, sb:{x: 0, y:100} },
+///  ]
 /// }
 /// ```
-#[derive(Serialize, Deserialize)]
-pub struct Ring {
-    /// the radius of the ring (default is 50)
-    #[serde(default = "Ring::default_radius")]
-    pub radius: f64,
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct AlongPath {
+    /// the path to place the objects on (same format as in `line`)
+    path: Path,
 
-    /// color from the palette to draw with
-    #[serde(default = "Color::default")]
-    pub color: Color,
+    /// the query used to find the object which should be placed.
+    pub query: Query,
 
-    /// tags of this object which can be used to query.
+    /// number of objects to place along the path (default is 10)
+    #[serde(default = "AlongPath::default_count")]
+    pub count: i32,
+
+    /// rotate the objects in the direction of the path (default is false)
     #[serde(default)]
-    pub tags: Vec<String>,
-}
+    pub rotate: bool,
 
-impl Ring {
-    fn default_radius() -> f64 {
-        50.0
-    }
-}
+    /// treat the path as closed, so the objects are spread
+    /// also on the way back from the last to the first point (default is false)
+    #[serde(default)]
+    pub closed: bool,
 
-impl Rendable for Ring {
-    fn render(&self, context: &Context, image_context: &ImageContext, _depth: i32) {
-        self.configure_color(&self.color, context, image_context);
-        context.arc(0.0, 0.0, self.radius, 0.0, 2.0 * std::f64::consts::PI);
-        self.stroke_and_preserve_line_width(&context);
-    }
-}
+    /// angle (in degree) to rotate (default is 0)
+    #[serde(default)]
+    pub angle: f64,
 
-/// draw a circle with filling
-///
-/// #Example
-///
-/// ```json
-/// {
-///  "type": "circle",
-///  "radius":50,
-/// }
-/// ```
-#[derive(Serialize, Deserialize)]
-pub struct Circle {
-    /// the radius of the ring (default is 50)
-    #[serde(default = "Circle::default_radius")]
-    pub radius: f64,
+    /// rescale (default is 100 which means no resizing)
+    #[serde(default = "AlongPath::default_scale")]
+    pub scale: f64,
 
-    /// color from the palette to draw with
-    #[serde(default = "Color::default")]
-    pub color: Color,
+    /// rescale for each level of recursion (in percent like `scale`),
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub scale_by_depth: Vec<f64>,
+
+    /// x coordinate of center (default is 0)
+    #[serde(default)]
+    pub x: f64,
+
+    /// y coordinate of center (default is 0)
+    #[serde(default)]
+    pub y: f64,
 
     /// tags of this object which can be used to query.
     #[serde(default)]
     pub tags: Vec<String>,
 }
 
-impl Circle {
-    fn default_radius() -> f64 {
-        50.0
+impl AlongPath {
+    fn default_scale() -> f64 {
+        100.0
+    }
+    fn default_count() -> i32 {
+        10
     }
 }
 
-impl Rendable for Circle {
-    fn render(&self, context: &Context, image_context: &ImageContext, _depth: i32) {
-        self.configure_color(&self.color, context, image_context);
-        context.arc(0.0, 0.0, self.radius, 0.0, 2.0 * std::f64::consts::PI);
-        context.fill();
+impl Rendable for AlongPath {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        context.save();
+
+        context.translate(self.x, self.y);
+        context.rotate(degree_to_radian(self.angle));
+
+        // stop rendering when scale is to small (or only draw the fallback)
+        let depth = match image_context.depth_for_scale(context, &self.query, depth) {
+            Some(depth) => depth,
+            None => {
+                context.restore();
+                return;
+            }
+        };
+
+        let scale = self.scale * 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth);
+        let polyline = Polyline::new(&self.path.segments(), self.closed);
+        let mut query_result = image_context.get_element_from_query(&self.query, depth);
+
+        for index in 0..self.count {
+            let distance = if self.closed {
+                polyline.length * f64::from(index) / f64::from(self.count)
+            } else if self.count > 1 {
+                polyline.length * f64::from(index) / f64::from(self.count - 1)
+            } else {
+                polyline.length / 2.0
+            };
+            let (x, y, direction) = match polyline.point_at(distance) {
+                None => break,
+                Some(point) => point,
+            };
+
+            if let Some(rendable) = query_result.next() {
+                context.save();
+                context.translate(x, y);
+                if self.rotate {
+                    context.rotate(direction);
+                }
+                context.scale(0.01 * scale, 0.01 * scale);
+                if image_context.is_visible(context, *rendable, depth - 1) {
+                    rendable.render(context, image_context, depth - 1);
+                }
+                context.restore();
+            }
+        }
+
+        context.restore();
+    }
+
+    fn bounds(&self, image_context: &ImageContext, depth: i32) -> Option<Bounds> {
+        if self.count < 1 {
+            return Some(Bounds::empty());
+        }
+        // the objects are somewhere on the path
+        let path = Bounds::of_segments(&self.path.segments());
+        let scale = self.scale * 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth);
+        let bounds = image_context.query_bounds(&self.query, depth, |child, _| {
+            let child = if self.rotate {
+                child.any_rotation()
+            } else {
+                child
+            };
+            child.scale(0.01 * scale).spread(&path)
+        })?;
+        Some(
+            bounds
+                .rotate(degree_to_radian(self.angle))
+                .translate(self.x, self.y),
+        )
     }
 }
 
-/// draw an icon
-/// it always is filled with color.
+/// A container to draw objects on a spiral.
+/// Every object is placed a bit further on the spiral
+/// than the previous one.
 ///
-/// #Example
+/// # Example
 ///
 /// ```json
 /// {
-///  "type": "icon",
-///  "path":[
-///  { x: 0, y: 0},
-///  { x: 0, y: 0, sa: {x: 0, y:0}, sb:{x: 0, y:0} },
-///  ]
+///  "type": "spiral",
+///  "kind": "logarithmic",
+///  "count": 60,
+///  "turns": 3,
+///  "growth": 80,
+///  "scale_step": 1,
+///  "query": {"by_name":"seed"}
 /// }
 /// ```
-#[derive(Serialize, Deserialize)]
-pub struct Icon {
-    /// path to draw the
-    path: Vec<IconPoint>,
-
-    /// color from the palette to draw with
-    #[serde(default = "Color::default")]
-    pub color: Color,
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Spiral {
+    /// the query used to find the object which should be placed.
+    pub query: Query,
 
-    /// tags of this object which can be used to query.
+    /// the kind of spiral (default is `archimedean`)
     #[serde(default)]
-    pub tags: Vec<String>,
-}
+    pub kind: SpiralKind,
 
-impl Rendable for Icon {
-    fn render(&self, context: &Context, image_context: &ImageContext, _depth: i32) {
-        self.configure_color(&self.color, context, image_context);
+    /// number of objects to place on the spiral (default is 50)
+    #[serde(default = "Spiral::default_count")]
+    pub count: i32,
 
-        let mut first = true;
-        for path in self.path.iter() {
-            if first {
-                context.move_to(path.x, path.y);
-                first = false;
-            } else {
-                if path.sa.is_none() && path.sb.is_none() {
-                    context.line_to(path.x, path.y);
-                } else {
-                    let sb = if path.sb.is_some() {
-                        Point {
-                            x: path.sb.as_ref().unwrap().x,
-                            y: path.sb.as_ref().unwrap().y,
-                        }
-                    } else {
-                        Point {
-                            x: path.x,
-                            y: path.y,
-                        }
-                    };
-                    let sa = if path.sa.is_some() {
-                        Point {
-                            x: path.sa.as_ref().unwrap().x,
-                            y: path.sa.as_ref().unwrap().y,
-                        }
-                    } else {
-                        Point {
-                            x: path.x,
-                            y: path.y,
-                        }
-                    };
-                    context.curve_to(sa.x, sa.y, sb.x, sb.y, path.x, path.y);
-                };
-            }
-        }
-        context.close_path();
-        context.fill();
-    }
-}
+    /// how often the spiral turns around the center (default is 3)
+    #[serde(default = "Spiral::default_turns")]
+    pub turns: f64,
 
-#[derive(Serialize, Deserialize)]
-pub struct IconPoint {
-    /// x coordinate
+    /// distance of the first object to the center (default is 10)
+    #[serde(default = "Spiral::default_radius")]
+    pub radius: f64,
+
+    /// how much the spiral grows per turn (default is 30).
+    /// for `archimedean` spirals this is the added distance to the center,
+    /// for `logarithmic` spirals this is the growth of the distance in percent.
+    #[serde(default = "Spiral::default_growth")]
+    pub growth: f64,
+
+    /// rotate the objects to point away from the center (default is false)
     #[serde(default)]
-    pub x: f64,
+    pub rotate: bool,
 
-    /// y coordinate
+    /// angle (in degree) every placed object is rotated
+    /// more than the previous one (default is 0)
     #[serde(default)]
-    pub y: f64,
+    pub rotation_step: f64,
+
+    /// added to `scale` for every placed object (default is 0)
+    #[serde(default)]
+    pub scale_step: f64,
+
+    /// angle (in degree) to rotate (default is 0)
+    #[serde(default)]
+    pub angle: f64,
+
+    /// rescale (default is 100 which means no resizing)
+    #[serde(default = "Spiral::default_scale")]
+    pub scale: f64,
+
+    /// rescale for each level of recursion (in percent like `scale`),
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub scale_by_depth: Vec<f64>,
+
+    /// x coordinate of center (default is 0)
+    #[serde(default)]
+    pub x: f64,
+
+    /// y coordinate of center (default is 0)
+    #[serde(default)]
+    pub y: f64,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// The kind of spiral to draw.
+/// (default is `archimedean`)
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub enum SpiralKind {
+    /// the distance between the turns is always the same.
+    #[serde(rename = "archimedean")]
+    #[default]
+    Archimedean,
+
+    /// the distance between the turns grows with every turn.
+    #[serde(rename = "logarithmic")]
+    Logarithmic,
+}
+
+impl Spiral {
+    fn default_scale() -> f64 {
+        100.0
+    }
+    fn default_count() -> i32 {
+        50
+    }
+    fn default_turns() -> f64 {
+        3.0
+    }
+    fn default_radius() -> f64 {
+        10.0
+    }
+    fn default_growth() -> f64 {
+        30.0
+    }
+
+    /// distance to the center after the given number of turns
+    fn radius_at(&self, turns: f64) -> f64 {
+        match self.kind {
+            SpiralKind::Archimedean => self.radius + self.growth * turns,
+            SpiralKind::Logarithmic => self.radius * (1.0 + 0.01 * self.growth).powf(turns),
+        }
+    }
+}
+
+impl Rendable for Spiral {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        context.save();
+
+        context.translate(self.x, self.y);
+        context.rotate(degree_to_radian(self.angle));
+
+        // stop rendering when scale is to small (or only draw the fallback)
+        let depth = match image_context.depth_for_scale(context, &self.query, depth) {
+            Some(depth) => depth,
+            None => {
+                context.restore();
+                return;
+            }
+        };
+
+        let by_depth = 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth);
+        let mut query_result = image_context.get_element_from_query(&self.query, depth);
+
+        for index in 0..self.count {
+            let scale = (self.scale + f64::from(index) * self.scale_step) * by_depth;
+            if scale <= 0.0 {
+                continue;
+            }
+            let turns = self.turns * f64::from(index) / f64::from(self.count);
+            let direction = 2.0 * std::f64::consts::PI * turns;
+            let radius = self.radius_at(turns);
+
+            if let Some(rendable) = query_result.next() {
+                context.save();
+                context.translate(radius * direction.cos(), radius * direction.sin());
+                let mut angle = f64::from(index) * self.rotation_step;
+                if self.rotate {
+                    angle += 90.0;
+                    context.rotate(direction);
+                }
+                context.rotate(degree_to_radian(angle));
+                context.scale(0.01 * scale, 0.01 * scale);
+                if image_context.is_visible(context, *rendable, depth - 1) {
+                    rendable.render(context, image_context, depth - 1);
+                }
+                context.restore();
+            }
+        }
+
+        context.restore();
+    }
+
+    fn bounds(&self, image_context: &ImageContext, depth: i32) -> Option<Bounds> {
+        let by_depth = 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth);
+        let bounds = image_context.query_bounds(&self.query, depth, |child, _| {
+            let child = child.any_rotation();
+            (0..self.count).fold(Bounds::empty(), |bounds, index| {
+                let scale = (self.scale + f64::from(index) * self.scale_step) * by_depth;
+                if scale <= 0.0 {
+                    return bounds;
+                }
+                let turns = self.turns * f64::from(index) / f64::from(self.count);
+                let direction = 2.0 * std::f64::consts::PI * turns;
+                let radius = self.radius_at(turns);
+                bounds.union(
+                    &child
+                        .scale(0.01 * scale)
+                        .translate(radius * direction.cos(), radius * direction.sin()),
+                )
+            })
+        })?;
+        Some(
+            bounds
+                .rotate(degree_to_radian(self.angle))
+                .translate(self.x, self.y),
+        )
+    }
+}
+
+/// A container to split an area randomly in rectangles
+/// and draw an object in each of them.
+/// The area is split horizontally or vertically again and again,
+/// until `levels` splits are done (or a rectangle randomly
+/// is not split any further).
+///
+/// The objects are scaled to fill their rectangle,
+/// assuming they have the size of 100 x 100 (like a `circle` with radius 50).
+///
+/// # Example
+///
+/// ```json
+/// {
+///  "type": "subdivide",
+///  "width": 300,
+///  "height": 200,
+///  "levels": 5,
+///  "scale": 90,
+///  "query": {"by_tag":["block"]}
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Subdivide {
+    /// the query used to find the object which should be placed.
+    pub query: Query,
+
+    /// width of the area (default is 100)
+    #[serde(default = "Subdivide::default_width")]
+    pub width: f64,
+
+    /// height of the area (default is 100)
+    #[serde(default = "Subdivide::default_height")]
+    pub height: f64,
+
+    /// how often the area is split at most (default is 4, at most 16)
+    #[serde(default = "Subdivide::default_levels")]
+    pub levels: i32,
+
+    /// chance (in percent) that a rectangle is split (default is 80)
+    #[serde(default = "Subdivide::default_split_chance")]
+    pub split_chance: f64,
+
+    /// smallest position (in percent) to split a rectangle at (default is 30)
+    #[serde(default = "Subdivide::default_min_ratio")]
+    pub min_ratio: f64,
+
+    /// biggest position (in percent) to split a rectangle at (default is 70)
+    #[serde(default = "Subdivide::default_max_ratio")]
+    pub max_ratio: f64,
+
+    /// keep the proportions of the objects instead of
+    /// stretching them to fill the rectangle (default is false)
+    #[serde(default)]
+    pub keep_aspect: bool,
+
+    /// angle (in degree) to rotate (default is 0)
+    #[serde(default)]
+    pub angle: f64,
+
+    /// rescale objects inside their rectangle
+    /// (default is 100 which means the rectangle is filled)
+    #[serde(default = "Subdivide::default_scale")]
+    pub scale: f64,
+
+    /// rescale for each level of recursion (in percent like `scale`),
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub scale_by_depth: Vec<f64>,
+
+    /// x coordinate of center (default is 0)
+    #[serde(default)]
+    pub x: f64,
+
+    /// y coordinate of center (default is 0)
+    #[serde(default)]
+    pub y: f64,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// a rectangle given by its center and its size
+struct Rectangle {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Subdivide {
+    /// levels are never deeper than this, so there are at most 65536 rectangles
+    const MAX_LEVELS: i32 = 16;
+
+    fn default_scale() -> f64 {
+        100.0
+    }
+    fn default_width() -> f64 {
+        100.0
+    }
+    fn default_height() -> f64 {
+        100.0
+    }
+    fn default_levels() -> i32 {
+        4
+    }
+    fn default_split_chance() -> f64 {
+        80.0
+    }
+    fn default_min_ratio() -> f64 {
+        30.0
+    }
+    fn default_max_ratio() -> f64 {
+        70.0
+    }
+
+    /// the rectangles of the whole area
+    fn leaves(&self, rng: &mut StdRng) -> Vec<Rectangle> {
+        let mut leaves = Vec::new();
+        self.split(
+            rng,
+            Rectangle {
+                x: 0.0,
+                y: 0.0,
+                width: self.width,
+                height: self.height,
+            },
+            i32::min(self.levels, Subdivide::MAX_LEVELS),
+            &mut leaves,
+        );
+        leaves
+    }
+
+    /// split the rectangle until `levels` is reached
+    fn split(
+        &self,
+        rng: &mut StdRng,
+        rectangle: Rectangle,
+        levels: i32,
+        leaves: &mut Vec<Rectangle>,
+    ) {
+        if levels < 1 || rng.gen_range(0.0, 100.0) >= self.split_chance {
+            leaves.push(rectangle);
+            return;
+        }
+
+        let ratio = if self.min_ratio < self.max_ratio {
+            0.01 * rng.gen_range(self.min_ratio, self.max_ratio)
+        } else {
+            0.01 * self.min_ratio
+        };
+
+        if rng.gen() {
+            let left = rectangle.width * ratio;
+            let right = rectangle.width - left;
+            let x = rectangle.x - rectangle.width / 2.0;
+            self.split(
+                rng,
+                Rectangle {
+                    x: x + left / 2.0,
+                    y: rectangle.y,
+                    width: left,
+                    height: rectangle.height,
+                },
+                levels - 1,
+                leaves,
+            );
+            self.split(
+                rng,
+                Rectangle {
+                    x: x + left + right / 2.0,
+                    y: rectangle.y,
+                    width: right,
+                    height: rectangle.height,
+                },
+                levels - 1,
+                leaves,
+            );
+        } else {
+            let top = rectangle.height * ratio;
+            let bottom = rectangle.height - top;
+            let y = rectangle.y - rectangle.height / 2.0;
+            self.split(
+                rng,
+                Rectangle {
+                    x: rectangle.x,
+                    y: y + top / 2.0,
+                    width: rectangle.width,
+                    height: top,
+                },
+                levels - 1,
+                leaves,
+            );
+            self.split(
+                rng,
+                Rectangle {
+                    x: rectangle.x,
+                    y: y + top + bottom / 2.0,
+                    width: rectangle.width,
+                    height: bottom,
+                },
+                levels - 1,
+                leaves,
+            );
+        }
+    }
+}
+
+impl Rendable for Subdivide {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        context.save();
+
+        context.translate(self.x, self.y);
+        context.rotate(degree_to_radian(self.angle));
+
+        // stop rendering when scale is to small (or only draw the fallback)
+        let depth = match image_context.depth_for_scale(context, &self.query, depth) {
+            Some(depth) => depth,
+            None => {
+                context.restore();
+                return;
+            }
+        };
+
+        let scale = self.scale * 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth);
+        let leaves = self.leaves(&mut image_context.rng());
+
+        let mut query_result = image_context.get_element_from_query(&self.query, depth);
+        for leaf in leaves.iter() {
+            if leaf.width <= 0.0 || leaf.height <= 0.0 {
+                continue;
+            }
+            if let Some(rendable) = query_result.next() {
+                let (scale_x, scale_y) = if self.keep_aspect {
+                    let scale = f64::min(leaf.width, leaf.height);
+                    (scale, scale)
+                } else {
+                    (leaf.width, leaf.height)
+                };
+                context.save();
+                context.translate(leaf.x, leaf.y);
+                context.scale(0.0001 * scale * scale_x, 0.0001 * scale * scale_y);
+                if image_context.is_visible(context, *rendable, depth - 1) {
+                    rendable.render(context, image_context, depth - 1);
+                }
+                context.restore();
+            }
+        }
+
+        context.restore();
+    }
+
+    fn bounds(&self, image_context: &ImageContext, depth: i32) -> Option<Bounds> {
+        // every leaf is inside of the area, and not bigger than it
+        let area = Bounds::rectangle(self.width, self.height);
+        let scale = self.scale * 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth);
+        let biggest = 0.0001 * scale * f64::max(self.width.abs(), self.height.abs());
+        let bounds = image_context.query_bounds(&self.query, depth, |child, _| {
+            child.scale_up_to(biggest).spread(&area)
+        })?;
+        Some(
+            bounds
+                .rotate(degree_to_radian(self.angle))
+                .translate(self.x, self.y),
+        )
+    }
+}
+
+/// a path flattened to straight lines,
+/// used to measure distances along a path.
+struct Polyline {
+    /// first point of the path
+    start: Option<(f64, f64)>,
+    lines: Vec<((f64, f64), (f64, f64))>,
+    length: f64,
+}
+
+impl Polyline {
+    /// number of straight lines a spline is split into
+    const SPLINE_STEPS: i32 = 32;
+
+    fn new(segments: &[Segment], closed: bool) -> Polyline {
+        let mut lines = Vec::new();
+        let mut start = None;
+        let mut sub_path_start = (0.0, 0.0);
+        let mut current = (0.0, 0.0);
+        for segment in segments.iter() {
+            match segment {
+                Segment::MoveTo(x, y) => {
+                    sub_path_start = (*x, *y);
+                    current = (*x, *y);
+                }
+                Segment::LineTo(x, y) => {
+                    lines.push((current, (*x, *y)));
+                    current = (*x, *y);
+                }
+                Segment::CurveTo(x1, y1, x2, y2, x, y) => {
+                    let (x0, y0) = current;
+                    for step in 1..=Polyline::SPLINE_STEPS {
+                        let t = f64::from(step) / f64::from(Polyline::SPLINE_STEPS);
+                        let u = 1.0 - t;
+                        let point = (
+                            u * u * u * x0
+                                + 3.0 * u * u * t * x1
+                                + 3.0 * u * t * t * x2
+                                + t * t * t * x,
+                            u * u * u * y0
+                                + 3.0 * u * u * t * y1
+                                + 3.0 * u * t * t * y2
+                                + t * t * t * y,
+                        );
+                        lines.push((current, point));
+                        current = point;
+                    }
+                }
+                Segment::ClosePath => {
+                    lines.push((current, sub_path_start));
+                    current = sub_path_start;
+                }
+            }
+            if start.is_none() {
+                start = Some(current);
+            }
+        }
+        if closed && current != sub_path_start {
+            lines.push((current, sub_path_start));
+        }
+
+        let length = lines
+            .iter()
+            .map(|((x0, y0), (x1, y1))| f64::hypot(x1 - x0, y1 - y0))
+            .sum();
+
+        Polyline {
+            start,
+            lines,
+            length,
+        }
+    }
+
+    /// find the point (and the direction as angle in radian)
+    /// after walking `distance` along the path.
+    fn point_at(&self, distance: f64) -> Option<(f64, f64, f64)> {
+        let mut walked = 0.0;
+        let mut last = self.start.map(|(x, y)| (x, y, 0.0));
+        for ((x0, y0), (x1, y1)) in self.lines.iter() {
+            let length = f64::hypot(x1 - x0, y1 - y0);
+            if length == 0.0 {
+                continue;
+            }
+            let direction = f64::atan2(y1 - y0, x1 - x0);
+            if walked + length >= distance {
+                let t = (distance - walked) / length;
+                return Some((x0 + t * (x1 - x0), y0 + t * (y1 - y0), direction));
+            }
+            walked += length;
+            last = Some((*x1, *y1, direction));
+        }
+        last
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Point {
+    #[serde(default)]
+    pub x: f64,
+    #[serde(default)]
+    pub y: f64,
+}
+
+impl Default for Point {
+    fn default() -> Self {
+        Point { x: 0.0, y: 0.0 }
+    }
+}
+
+/// draw a ring (without filling)
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "ring",
+///  "radius":50,
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Ring {
+    /// the radius of the ring (default is 50)
+    #[serde(default = "Ring::default_radius")]
+    pub radius: f64,
+
+    /// color from the palette to draw with
+    #[serde(default = "Color::default")]
+    pub color: Color,
+
+    /// colors to use instead of `color`, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Ring {
+    fn default_radius() -> f64 {
+        50.0
+    }
+}
+
+impl Rendable for Ring {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        self.configure_color(
+            image_context.color_by_depth(&self.color, &self.color_by_depth, depth),
+            context,
+            image_context,
+        );
+        context.arc(0.0, 0.0, self.radius, 0.0, 2.0 * std::f64::consts::PI);
+        self.stroke_and_preserve_line_width(context);
+    }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        Some(Bounds::around(self.radius))
+    }
+}
+
+/// draw a circle with filling
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "circle",
+///  "radius":50,
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Circle {
+    /// the radius of the ring (default is 50)
+    #[serde(default = "Circle::default_radius")]
+    pub radius: f64,
+
+    /// color from the palette to draw with
+    #[serde(default = "Color::default")]
+    pub color: Color,
+
+    /// colors to use instead of `color`, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Circle {
+    fn default_radius() -> f64 {
+        50.0
+    }
+}
+
+impl Rendable for Circle {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        self.configure_color(
+            image_context.color_by_depth(&self.color, &self.color_by_depth, depth),
+            context,
+            image_context,
+        );
+        context.arc(0.0, 0.0, self.radius, 0.0, 2.0 * std::f64::consts::PI);
+        context.fill();
+    }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        Some(Bounds::around(self.radius))
+    }
+}
+
+/// draw a part of a ring (without filling).
+/// angles start on the right and grow clockwise.
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "arc",
+///  "radius":50,
+///  "start":0,
+///  "end":90,
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Arc {
+    /// the radius of the arc (default is 50)
+    #[serde(default = "Arc::default_radius")]
+    pub radius: f64,
+
+    /// angle (in degree) to start the arc (default is 0)
+    #[serde(default)]
+    pub start: f64,
+
+    /// angle (in degree) to end the arc (default is 90)
+    #[serde(default = "Arc::default_end")]
+    pub end: f64,
+
+    /// color from the palette to draw with
+    #[serde(default = "Color::default")]
+    pub color: Color,
+
+    /// colors to use instead of `color`, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Arc {
+    fn default_radius() -> f64 {
+        50.0
+    }
+    fn default_end() -> f64 {
+        90.0
+    }
+}
+
+impl Rendable for Arc {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        self.configure_color(
+            image_context.color_by_depth(&self.color, &self.color_by_depth, depth),
+            context,
+            image_context,
+        );
+        context.new_path();
+        context.arc(
+            0.0,
+            0.0,
+            self.radius,
+            degree_to_radian(self.start),
+            degree_to_radian(self.end),
+        );
+        self.stroke_and_preserve_line_width(context);
+    }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        Some(Bounds::around(self.radius))
+    }
+}
+
+/// draw a filled part of a circle (like a piece of pie).
+/// angles start on the right and grow clockwise.
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "pie",
+///  "radius":50,
+///  "start":0,
+///  "end":90,
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Pie {
+    /// the radius of the pie (default is 50)
+    #[serde(default = "Pie::default_radius")]
+    pub radius: f64,
+
+    /// angle (in degree) to start the pie (default is 0)
+    #[serde(default)]
+    pub start: f64,
+
+    /// angle (in degree) to end the pie (default is 90)
+    #[serde(default = "Pie::default_end")]
+    pub end: f64,
+
+    /// color from the palette to draw with
+    #[serde(default = "Color::default")]
+    pub color: Color,
+
+    /// colors to use instead of `color`, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Pie {
+    fn default_radius() -> f64 {
+        50.0
+    }
+    fn default_end() -> f64 {
+        90.0
+    }
+}
+
+impl Rendable for Pie {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        self.configure_color(
+            image_context.color_by_depth(&self.color, &self.color_by_depth, depth),
+            context,
+            image_context,
+        );
+        context.move_to(0.0, 0.0);
+        context.arc(
+            0.0,
+            0.0,
+            self.radius,
+            degree_to_radian(self.start),
+            degree_to_radian(self.end),
+        );
+        context.close_path();
+        context.fill();
+    }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        Some(Bounds::around(self.radius))
+    }
+}
+
+/// draw an ellipse
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "ellipse",
+///  "rx":50,
+///  "ry":25,
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Ellipse {
+    /// the horizontal radius of the ellipse (default is 50)
+    #[serde(default = "Ellipse::default_rx")]
+    pub rx: f64,
+
+    /// the vertical radius of the ellipse (default is 25)
+    #[serde(default = "Ellipse::default_ry")]
+    pub ry: f64,
+
+    /// fill the ellipse, or only draw the outline (default is true)
+    #[serde(default = "Ellipse::default_filled")]
+    pub filled: bool,
+
+    /// color from the palette to draw with
+    #[serde(default = "Color::default")]
+    pub color: Color,
+
+    /// colors to use instead of `color`, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Ellipse {
+    fn default_rx() -> f64 {
+        50.0
+    }
+    fn default_ry() -> f64 {
+        25.0
+    }
+    fn default_filled() -> bool {
+        true
+    }
+}
+
+impl Rendable for Ellipse {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        if self.rx <= 0.0 || self.ry <= 0.0 {
+            return;
+        }
+        self.configure_color(
+            image_context.color_by_depth(&self.color, &self.color_by_depth, depth),
+            context,
+            image_context,
+        );
+        context.save();
+        context.scale(self.rx, self.ry);
+        context.new_path();
+        context.arc(0.0, 0.0, 1.0, 0.0, 2.0 * std::f64::consts::PI);
+        context.restore();
+        if self.filled {
+            context.fill();
+        } else {
+            self.stroke_and_preserve_line_width(context);
+        }
+    }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        if self.rx <= 0.0 || self.ry <= 0.0 {
+            return Some(Bounds::empty());
+        }
+        Some(Bounds::rectangle(2.0 * self.rx, 2.0 * self.ry))
+    }
+}
+
+/// draw an icon
+/// it always is filled with color.
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "icon",
+///  "path":[
+///  { x: 0, y: 0},
+///  { x: 0, y: 0, sa: {x: 0, y:0}, sb:{x: 0, y:0} },
+///  ]
+/// }
+/// ```
+///
+/// ```json
+/// {
+///  "type": "icon",
+///  "path": "M -50 -50 L -50 50 L 50 0 Z"
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Icon {
+    /// path to draw the
+    path: Path,
+
+    /// how to decide what is inside of the path (default is `winding`)
+    #[serde(default)]
+    pub fill_rule: FillRule,
+
+    /// color from the palette to draw with
+    #[serde(default = "Color::default")]
+    pub color: Color,
+
+    /// colors to use instead of `color`, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Rendable for Icon {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        self.configure_color(
+            image_context.color_by_depth(&self.color, &self.color_by_depth, depth),
+            context,
+            image_context,
+        );
+        path::draw(context, &self.path.segments());
+        context.close_path();
+        context.save();
+        context.set_fill_rule(match self.fill_rule {
+            FillRule::Winding => cairo::FillRule::Winding,
+            FillRule::EvenOdd => cairo::FillRule::EvenOdd,
+        });
+        context.fill();
+        context.restore();
+    }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        Some(Bounds::of_segments(&self.path.segments()))
+    }
+}
+
+/// draw the shapes of an svg file.
+///
+/// The svg is fitted into a square of `size` and centered.
+/// Colors of the svg are replaced by colors of the palette,
+/// using `colors` to map from the svg color to the palette color
+/// (`color` is used for all colors not in `colors`).
+/// Strokes are drawn with the `line_size` of the picture.
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "svg",
+///  "file": "icons/star.svg",
+///  "colors": { "#ffffff": "background" }
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Svg {
+    /// svg file to draw (relative to the `input.json`)
+    pub file: String,
+
+    /// size of the square the svg is fitted into (default is 100)
+    #[serde(default = "Svg::default_size")]
+    pub size: f64,
+
+    /// palette color for each color of the svg,
+    /// like `{ "#000000": "fill", "white": "background" }`
+    #[serde(default)]
+    pub colors: HashMap<String, Color>,
+
+    /// color from the palette for all colors not in `colors` (default is `fill`)
+    #[serde(default = "Color::default")]
+    pub color: Color,
+
+    /// colors to use instead of `color`, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// the shapes, loaded with the structure
+    #[serde(skip)]
+    drawing: Option<svg::Drawing>,
+}
+
+impl Svg {
+    fn default_size() -> f64 {
+        100.0
+    }
+
+    /// load the svg file
+    pub fn load(&mut self, directory: &std::path::Path) -> Result<(), Box<dyn Error>> {
+        self.drawing = Some(svg::load(&directory.join(&self.file))?);
+        // match colors the same way the colors of the svg are matched
+        self.colors = self
+            .colors
+            .drain()
+            .map(|(key, color)| (svg::normalize_color(&key).unwrap_or(key), color))
+            .collect();
+        Ok(())
+    }
+
+    /// the palette color for a color of the svg, `default` is used for all others
+    fn palette_color<'c>(&'c self, color: &str, default: &'c Color) -> &'c Color {
+        self.colors.get(color).unwrap_or(default)
+    }
+}
+
+impl Rendable for Svg {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        let drawing = match &self.drawing {
+            Some(drawing) => drawing,
+            None => return,
+        };
+        let (x, y, width, height) = drawing.view_box;
+        let extent = width.max(height);
+        if extent <= 0.0 {
+            return;
+        }
+
+        let color = image_context.color_by_depth(&self.color, &self.color_by_depth, depth);
+        context.save();
+        context.scale(self.size / extent, self.size / extent);
+        context.translate(-x - width / 2.0, -y - height / 2.0);
+        for shape in drawing.shapes.iter() {
+            if let Some(fill) = &shape.fill {
+                self.configure_color(self.palette_color(fill, color), context, image_context);
+                path::draw(context, &shape.segments);
+                context.save();
+                context.set_fill_rule(if shape.even_odd {
+                    cairo::FillRule::EvenOdd
+                } else {
+                    cairo::FillRule::Winding
+                });
+                context.fill();
+                context.restore();
+            }
+            if let Some(stroke) = &shape.stroke {
+                self.configure_color(self.palette_color(stroke, color), context, image_context);
+                path::draw(context, &shape.segments);
+                self.stroke_and_preserve_line_width(context);
+            }
+        }
+        context.restore();
+    }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        let drawing = match &self.drawing {
+            Some(drawing) => drawing,
+            None => return Some(Bounds::empty()),
+        };
+        let (x, y, width, height) = drawing.view_box;
+        let extent = width.max(height);
+        if extent <= 0.0 {
+            return Some(Bounds::empty());
+        }
+        let bounds = drawing
+            .shapes
+            .iter()
+            .fold(Bounds::empty(), |bounds, shape| {
+                bounds.union(&Bounds::of_segments(&shape.segments))
+            });
+        Some(
+            bounds
+                .translate(-x - width / 2.0, -y - height / 2.0)
+                .scale(self.size / extent),
+        )
+    }
+}
+
+/// draw a png image, centered.
+///
+/// The image is stretched to `width` and `height`.
+/// With `tint` only the shape of the image (its transparency)
+/// is drawn with a color of the palette.
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "image",
+///  "file": "logos/logo.png",
+///  "width": 100,
+///  "height": 50,
+///  "tint": "fill",
+///  "opacity": 80
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Image {
+    /// png file to draw (relative to the `input.json`)
+    pub file: String,
+
+    /// width (default is 100)
+    #[serde(default = "Image::default_width")]
+    pub width: f64,
+
+    /// height (default is 100)
+    #[serde(default = "Image::default_height")]
+    pub height: f64,
+
+    /// color from the palette to draw the image with
+    /// (default is none, which keeps the colors of the image)
+    #[serde(default)]
+    pub tint: Option<Color>,
+
+    /// opacity in percent (default is 100)
+    #[serde(default = "Image::default_opacity")]
+    pub opacity: f64,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// the image, loaded with the structure
+    #[serde(skip)]
+    surface: Option<cairo::ImageSurface>,
+}
+
+impl Image {
+    fn default_width() -> f64 {
+        100.0
+    }
+    fn default_height() -> f64 {
+        100.0
+    }
+    fn default_opacity() -> f64 {
+        100.0
+    }
+
+    /// load the png file
+    pub fn load(&mut self, directory: &std::path::Path) -> Result<(), Box<dyn Error>> {
+        let file = directory.join(&self.file);
+        let surface = File::open(&file)
+            .map_err(|error| error.to_string())
+            .and_then(|mut reader| {
+                cairo::ImageSurface::create_from_png(&mut reader).map_err(|error| error.to_string())
+            })
+            .map_err(|error| format!("can't load image {} : {}", file.display(), error))?;
+        self.surface = Some(surface);
+        Ok(())
+    }
+}
+
+impl Rendable for Image {
+    fn render(&self, context: &Context, image_context: &ImageContext, _depth: i32) {
+        let surface = match &self.surface {
+            Some(surface) => surface,
+            None => return,
+        };
+        if surface.get_width() <= 0 || surface.get_height() <= 0 {
+            return;
+        }
+        let alpha = (0.01 * self.opacity).clamp(0.0, 1.0);
+
+        context.save();
+        context.translate(-self.width / 2.0, -self.height / 2.0);
+        context.scale(
+            self.width / f64::from(surface.get_width()),
+            self.height / f64::from(surface.get_height()),
+        );
+        match &self.tint {
+            Some(color) => {
+                self.configure_color_with_alpha(color, alpha, context, image_context);
+                context.mask_surface(surface, 0.0, 0.0);
+            }
+            None => {
+                context.set_source_surface(surface, 0.0, 0.0);
+                context.paint_with_alpha(alpha);
+            }
+        }
+        context.restore();
+    }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        Some(Bounds::rectangle(self.width, self.height))
+    }
+}
+
+/// Decides which parts of a path with multiple sub paths are filled.
+/// (default is `winding`)
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub enum FillRule {
+    /// fill everything inside, except sub paths that go in the opposite direction
+    #[serde(rename = "winding")]
+    #[default]
+    Winding,
+
+    /// fill every second area, so sub paths inside of other sub paths are holes
+    #[serde(rename = "even_odd")]
+    EvenOdd,
+}
+
+/// A path, either as list of points
+/// or as svg path data (the `d` attribute of an svg `path`).
+///
+/// #Example
+///
+/// ```json
+/// [{ x: 0, y: 0}, { x: 10, y: 0, sa: {x: 5, y:5}, sb:{x: 5, y:-5} }]
+/// ```
+///
+/// ```json
+/// "M 0 0 C 5 5 5 -5 10 0"
+/// ```
+#[derive(Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Path {
+    /// list of points, connected by lines or splines
+    Points(Vec<IconPoint>),
+
+    /// svg path data
+    Data(PathData),
+}
+
+/// not derived (untagged), to keep the errors of the path data
+impl<'de> Deserialize<'de> for Path {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Path, D::Error> {
+        struct PathVisitor;
+
+        impl<'de> Visitor<'de> for PathVisitor {
+            type Value = Path;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a list of points or svg path data")
+            }
+
+            fn visit_str<E: de::Error>(self, data: &str) -> Result<Path, E> {
+                PathData::try_from(data.to_string())
+                    .map(Path::Data)
+                    .map_err(E::custom)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut sequence: A) -> Result<Path, A::Error> {
+                let mut points = Vec::new();
+                while let Some(point) = sequence.next_element()? {
+                    points.push(point);
+                }
+                Ok(Path::Points(points))
+            }
+        }
+
+        deserializer.deserialize_any(PathVisitor)
+    }
+}
+
+impl Path {
+    /// the segments to draw this path
+    pub fn segments(&self) -> Vec<Segment> {
+        match self {
+            Path::Data(data) => data.segments().clone(),
+            Path::Points(points) => {
+                let mut segments = Vec::new();
+                for point in points.iter() {
+                    if segments.is_empty() || point.move_to {
+                        segments.push(Segment::MoveTo(point.x, point.y));
+                    } else if point.sa.is_none() && point.sb.is_none() {
+                        segments.push(Segment::LineTo(point.x, point.y));
+                    } else {
+                        let (sa_x, sa_y) = match &point.sa {
+                            Some(sa) => (sa.x, sa.y),
+                            None => (point.x, point.y),
+                        };
+                        let (sb_x, sb_y) = match &point.sb {
+                            Some(sb) => (sb.x, sb.y),
+                            None => (point.x, point.y),
+                        };
+                        segments.push(Segment::CurveTo(sa_x, sa_y, sb_x, sb_y, point.x, point.y));
+                    }
+                    if point.close {
+                        segments.push(Segment::ClosePath);
+                    }
+                }
+                segments
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct IconPoint {
+    /// x coordinate
+    #[serde(default)]
+    pub x: f64,
+
+    /// y coordinate
+    #[serde(default)]
+    pub y: f64,
 
     /// spline point of source
     sa: Option<Point>,
 
-    /// spline point of target (the point given by x and y)
-    sb: Option<Point>,
+    /// spline point of target (the point given by x and y)
+    sb: Option<Point>,
+
+    /// start a new sub path at this point,
+    /// instead of connecting it to the previous point (default is false)
+    #[serde(default)]
+    pub move_to: bool,
+
+    /// close the sub path after this point (default is false)
+    #[serde(default)]
+    pub close: bool,
+}
+
+/// draw an path
+/// it always is just the line with color.
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "path",
+///  "path":[
+///  { x: 0, y: 0},
+///  { x: 0, y: 0, sa: {x: 0, y:0}, sb:{x: 0, y:0} },
+///  ]
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Line {
+    /// path to draw the
+    path: Path,
+
+    /// color from the palette to draw with
+    #[serde(default = "Color::default")]
+    pub color: Color,
+
+    /// colors to use instead of `color`, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Rendable for Line {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        self.configure_color(
+            image_context.color_by_depth(&self.color, &self.color_by_depth, depth),
+            context,
+            image_context,
+        );
+        path::draw(context, &self.path.segments());
+        self.stroke_and_preserve_line_width(context);
+    }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        Some(Bounds::of_segments(&self.path.segments()))
+    }
+}
+
+/// draw a path created by a lindenmayer system (L-system).
+///
+/// The `axiom` is rewritten `iterations` times using the `rules`,
+/// but never more often than the recursion depth allows.
+/// The result is drawn by a turtle which starts in the center
+/// heading upwards and understands the following symbols.
+///
+/// * `F` and `G` move forward and draw a line
+/// * `f` and `g` move forward without drawing
+/// * `+` turn left by `angle`
+/// * `-` turn right by `angle`
+/// * `|` turn around
+/// * `[` remember position and heading
+/// * `]` go back to the last remembered position and heading
+///
+/// Symbols that have a query in `queries` place the found object
+/// at the position of the turtle, rotated in the heading of the turtle.
+/// All other symbols are ignored while drawing.
+/// The drawing is centered like all other objects.
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "lsystem",
+///  "axiom": "X",
+///  "rules": {"X": "F[+X]F[-X]+XL", "F": "FF"},
+///  "iterations": 4,
+///  "angle": 25,
+///  "step": 3,
+///  "queries": {"L": {"by_name":"leaf"}}
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct LSystem {
+    /// the word to start with
+    pub axiom: String,
+
+    /// rewrite rules, every symbol is replaced by its rule (default is no rules)
+    #[serde(default)]
+    pub rules: HashMap<char, String>,
+
+    /// how often the rules are applied (default is 3)
+    #[serde(default = "LSystem::default_iterations")]
+    pub iterations: i32,
+
+    /// angle (in degree) the turtle turns on `+` and `-` (default is 90)
+    #[serde(default = "LSystem::default_angle")]
+    pub angle: f64,
+
+    /// length of one step of the turtle (default is 10)
+    #[serde(default = "LSystem::default_step")]
+    pub step: f64,
+
+    /// queries used to find the objects placed by symbols (default is none)
+    #[serde(default)]
+    pub queries: HashMap<char, Query>,
+
+    /// rescale objects placed by `queries` (default is 100 which means no resizing)
+    #[serde(default = "LSystem::default_scale")]
+    pub scale: f64,
+
+    /// rescale for each level of recursion (in percent like `scale`),
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub scale_by_depth: Vec<f64>,
+
+    /// color from the palette to draw with
+    #[serde(default = "Color::default")]
+    pub color: Color,
+
+    /// colors to use instead of `color`, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// what the turtle of an L-system does
+enum TurtleCommand {
+    MoveTo(f64, f64),
+    LineTo(f64, f64),
+    Place(f64, f64, f64, char),
+}
+
+impl TurtleCommand {
+    /// where the turtle is after the command
+    fn point(&self) -> (f64, f64) {
+        match self {
+            TurtleCommand::MoveTo(x, y) => (*x, *y),
+            TurtleCommand::LineTo(x, y) => (*x, *y),
+            TurtleCommand::Place(x, y, _, _) => (*x, *y),
+        }
+    }
+}
+
+impl LSystem {
+    /// the rewritten word will never be longer than this
+    const MAX_SYMBOLS: usize = 100_000;
+
+    fn default_iterations() -> i32 {
+        3
+    }
+    fn default_angle() -> f64 {
+        90.0
+    }
+    fn default_step() -> f64 {
+        10.0
+    }
+    fn default_scale() -> f64 {
+        100.0
+    }
+
+    /// apply the rules `iterations` times on the axiom
+    fn expand(&self, iterations: i32) -> String {
+        let mut word = self.axiom.clone();
+        for _ in 0..iterations {
+            let mut next = String::with_capacity(word.len());
+            for symbol in word.chars() {
+                match self.rules.get(&symbol) {
+                    None => next.push(symbol),
+                    Some(replacement) => next.push_str(replacement),
+                }
+                if next.len() > LSystem::MAX_SYMBOLS {
+                    return word;
+                }
+            }
+            word = next;
+        }
+        word
+    }
+
+    /// let the turtle walk along the word
+    fn walk(&self, word: &str) -> Vec<TurtleCommand> {
+        let turn = degree_to_radian(self.angle);
+        let mut x = 0.0;
+        let mut y = 0.0;
+        let mut heading = -std::f64::consts::FRAC_PI_2;
+        let mut stack = Vec::new();
+        let mut commands = vec![TurtleCommand::MoveTo(x, y)];
+
+        for symbol in word.chars() {
+            match symbol {
+                'F' | 'G' => {
+                    x += self.step * heading.cos();
+                    y += self.step * heading.sin();
+                    commands.push(TurtleCommand::LineTo(x, y));
+                }
+                'f' | 'g' => {
+                    x += self.step * heading.cos();
+                    y += self.step * heading.sin();
+                    commands.push(TurtleCommand::MoveTo(x, y));
+                }
+                '+' => heading -= turn,
+                '-' => heading += turn,
+                '|' => heading += std::f64::consts::PI,
+                '[' => stack.push((x, y, heading)),
+                ']' => {
+                    if let Some((last_x, last_y, last_heading)) = stack.pop() {
+                        x = last_x;
+                        y = last_y;
+                        heading = last_heading;
+                        commands.push(TurtleCommand::MoveTo(x, y));
+                    }
+                }
+                _ => {
+                    if self.queries.contains_key(&symbol) {
+                        commands.push(TurtleCommand::Place(x, y, heading, symbol));
+                    }
+                }
+            }
+        }
+        commands
+    }
+
+    /// the turtle commands drawn with `depth`, and the bounds of all their points
+    fn drawing(&self, depth: i32) -> (Vec<TurtleCommand>, Bounds) {
+        let iterations = i32::min(self.iterations, depth);
+        let commands = self.walk(&self.expand(iterations));
+        let area = Bounds::of_points(commands.iter().map(TurtleCommand::point));
+        (commands, area)
+    }
+}
+
+impl Rendable for LSystem {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        let (commands, area) = self.drawing(depth);
+
+        // center the drawing
+        context.save();
+        context.translate(
+            -(area.min_x + area.max_x) / 2.0,
+            -(area.min_y + area.max_y) / 2.0,
+        );
+
+        let scale = self.scale * 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth);
+        let color = image_context.color_by_depth(&self.color, &self.color_by_depth, depth);
+        let mut query_results = HashMap::new();
+        self.configure_color(color, context, image_context);
+        for command in commands.iter() {
+            match command {
+                TurtleCommand::MoveTo(x, y) => context.move_to(*x, *y),
+                TurtleCommand::LineTo(x, y) => context.line_to(*x, *y),
+                TurtleCommand::Place(x, y, heading, symbol) => {
+                    self.stroke_and_preserve_line_width(context);
+
+                    // stop placing objects when they are too small (or only place the fallback)
+                    let query_result = query_results.entry(*symbol).or_insert_with(|| {
+                        let query = &self.queries[symbol];
+                        image_context
+                            .depth_for_scale(context, query, depth)
+                            .map(|depth| {
+                                (depth, image_context.get_element_from_query(query, depth))
+                            })
+                    });
+                    if let Some((depth, query_result)) = query_result {
+                        if let Some(rendable) = query_result.next() {
+                            context.save();
+                            context.translate(*x, *y);
+                            context.rotate(heading + std::f64::consts::FRAC_PI_2);
+                            context.scale(0.01 * scale, 0.01 * scale);
+                            if image_context.is_visible(context, *rendable, *depth - 1) {
+                                rendable.render(context, image_context, *depth - 1);
+                            }
+                            context.restore();
+                        }
+                    }
+
+                    self.configure_color(color, context, image_context);
+                    context.move_to(*x, *y);
+                }
+            }
+        }
+        self.stroke_and_preserve_line_width(context);
+
+        context.restore();
+    }
+
+    fn bounds(&self, image_context: &ImageContext, depth: i32) -> Option<Bounds> {
+        let (commands, area) = self.drawing(depth);
+        let scale = self.scale * 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth);
+        let mut bounds = area;
+        for (symbol, query) in self.queries.iter() {
+            // the objects are placed somewhere on the places of the symbol
+            let places = Bounds::of_points(commands.iter().filter_map(|command| match command {
+                TurtleCommand::Place(x, y, _, placed) if placed == symbol => Some((*x, *y)),
+                _ => None,
+            }));
+            if places.is_empty() {
+                continue;
+            }
+            let placed = image_context.query_bounds(query, depth, |child, _| {
+                child.any_rotation().scale(0.01 * scale).spread(&places)
+            })?;
+            bounds = bounds.union(&placed);
+        }
+        Some(bounds.translate(
+            -(area.min_x + area.max_x) / 2.0,
+            -(area.min_y + area.max_y) / 2.0,
+        ))
+    }
+}
+
+/// draw the cells of a voronoi diagram.
+///
+/// Random points are placed in an area of `width` times `height`,
+/// every point gets the cell of the area that is closer to it
+/// than to any other point.
+///
+/// The cells can be stroked (using `color`), filled
+/// (using one of the `fill` colors for each cell) and can have
+/// an object placed on their point which is cut to the cell.
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "voronoi",
+///  "points": 30,
+///  "width": 300,
+///  "height": 200,
+///  "fill": ["fill", "background"],
+///  "query": {"by_name":"dot"}
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Voronoi {
+    /// number of random points (or cells) (default is 20, at most 1000)
+    #[serde(default = "Voronoi::default_points")]
+    pub points: i32,
+
+    /// width of the area (default is 100)
+    #[serde(default = "Voronoi::default_width")]
+    pub width: f64,
+
+    /// height of the area (default is 100)
+    #[serde(default = "Voronoi::default_height")]
+    pub height: f64,
+
+    /// draw the outline of the cells (default is true)
+    #[serde(default = "Voronoi::default_stroke")]
+    pub stroke: bool,
+
+    /// color from the palette to draw the outlines with
+    #[serde(default = "Color::default")]
+    pub color: Color,
+
+    /// colors to use instead of `color` for the outlines, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
+    /// colors from the palette to fill the cells with,
+    /// one is chosen randomly for every cell (default is no filling)
+    #[serde(default)]
+    pub fill: Vec<Color>,
+
+    /// the query used to find the objects which should be placed in the cells.
+    /// (default is no objects)
+    #[serde(default)]
+    pub query: Option<Query>,
+
+    /// rescale objects placed in the cells (default is 100 which means no resizing)
+    #[serde(default = "Voronoi::default_scale")]
+    pub scale: f64,
+
+    /// rescale for each level of recursion (in percent like `scale`),
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub scale_by_depth: Vec<f64>,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// a cell of a voronoi diagram
+struct VoronoiCell {
+    point: (f64, f64),
+    corners: Vec<(f64, f64)>,
+}
+
+impl Voronoi {
+    /// there are never more points than this, every cell is cut by all other points
+    const MAX_POINTS: i32 = 1000;
+
+    fn default_points() -> i32 {
+        20
+    }
+    fn default_width() -> f64 {
+        100.0
+    }
+    fn default_height() -> f64 {
+        100.0
+    }
+    fn default_stroke() -> bool {
+        true
+    }
+    fn default_scale() -> f64 {
+        100.0
+    }
+
+    /// create the random points and their cells
+    fn cells(&self, rng: &mut StdRng) -> Vec<VoronoiCell> {
+        let (half_width, half_height) = (self.width / 2.0, self.height / 2.0);
+        if half_width <= 0.0 || half_height <= 0.0 {
+            return Vec::new();
+        }
+        let points: Vec<(f64, f64)> = (0..i32::min(self.points, Voronoi::MAX_POINTS))
+            .map(|_| {
+                (
+                    rng.gen_range(-half_width, half_width),
+                    rng.gen_range(-half_height, half_height),
+                )
+            })
+            .collect();
+
+        let area = vec![
+            (-half_width, -half_height),
+            (half_width, -half_height),
+            (half_width, half_height),
+            (-half_width, half_height),
+        ];
+        points
+            .iter()
+            .map(|point| {
+                let corners = points
+                    .iter()
+                    .filter(|other| *other != point)
+                    .fold(area.clone(), |corners, other| {
+                        Voronoi::cut(&corners, *point, *other)
+                    });
+                VoronoiCell {
+                    point: *point,
+                    corners,
+                }
+            })
+            .collect()
+    }
+
+    /// cut away the part of the cell that is closer to `other` than to `point`
+    fn cut(cell: &[(f64, f64)], point: (f64, f64), other: (f64, f64)) -> Vec<(f64, f64)> {
+        let middle = ((point.0 + other.0) / 2.0, (point.1 + other.1) / 2.0);
+        let normal = (other.0 - point.0, other.1 - point.1);
+        let side = |corner: (f64, f64)| {
+            (corner.0 - middle.0) * normal.0 + (corner.1 - middle.1) * normal.1
+        };
+
+        let mut result = Vec::new();
+        for index in 0..cell.len() {
+            let current = cell[index];
+            let next = cell[(index + 1) % cell.len()];
+            let (current_side, next_side) = (side(current), side(next));
+            if current_side <= 0.0 {
+                result.push(current);
+            }
+            if (current_side <= 0.0) != (next_side <= 0.0) {
+                let t = current_side / (current_side - next_side);
+                result.push((
+                    current.0 + t * (next.0 - current.0),
+                    current.1 + t * (next.1 - current.1),
+                ));
+            }
+        }
+        result
+    }
+
+    fn draw_cell(context: &Context, cell: &VoronoiCell) {
+        for (index, (x, y)) in cell.corners.iter().enumerate() {
+            if index == 0 {
+                context.move_to(*x, *y);
+            } else {
+                context.line_to(*x, *y);
+            }
+        }
+        context.close_path();
+    }
+}
+
+impl Rendable for Voronoi {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        let cells = self.cells(&mut image_context.rng());
+
+        if !self.fill.is_empty() {
+            for cell in cells.iter() {
+                if let Some(color) = self.fill.choose(&mut *image_context.rng()) {
+                    self.configure_color(color, context, image_context);
+                    Voronoi::draw_cell(context, cell);
+                    context.fill();
+                }
+            }
+        }
+
+        // stop placing objects when they are too small (or only place the fallback)
+        let placing = self.query.as_ref().and_then(|query| {
+            image_context
+                .depth_for_scale(context, query, depth)
+                .map(|depth| (query, depth))
+        });
+        if let Some((query, depth)) = placing {
+            let scale =
+                self.scale * 0.01 * image_context.scale_by_depth(&self.scale_by_depth, depth);
+            let mut query_result = image_context.get_element_from_query(query, depth);
+            for cell in cells.iter() {
+                if let Some(rendable) = query_result.next() {
+                    context.save();
+                    Voronoi::draw_cell(context, cell);
+                    context.clip();
+                    context.translate(cell.point.0, cell.point.1);
+                    context.scale(0.01 * scale, 0.01 * scale);
+                    if image_context.is_visible(context, *rendable, depth - 1) {
+                        rendable.render(context, image_context, depth - 1);
+                    }
+                    context.restore();
+                }
+            }
+        }
+
+        if self.stroke {
+            self.configure_color(
+                image_context.color_by_depth(&self.color, &self.color_by_depth, depth),
+                context,
+                image_context,
+            );
+            for cell in cells.iter() {
+                Voronoi::draw_cell(context, cell);
+            }
+            self.stroke_and_preserve_line_width(context);
+        }
+    }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        // the placed objects are cut to their cells
+        Some(Bounds::rectangle(self.width, self.height))
+    }
+}
+
+/// draw lines following a noise field.
+///
+/// Lines start at random positions in an area of `width` times `height`
+/// and follow the direction given by a perlin noise at their position,
+/// until they made all their `steps` or left the area.
+///
+/// #Example
+///
+/// ```json
+/// {
+///  "type": "flow_field",
+///  "width": 300,
+///  "height": 200,
+///  "lines": 300,
+///  "steps": 40,
+///  "noise_scale": 0.5
+/// }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct FlowField {
+    /// number of lines to draw (default is 100)
+    #[serde(default = "FlowField::default_lines")]
+    pub lines: i32,
+
+    /// number of steps of each line (default is 20)
+    #[serde(default = "FlowField::default_steps")]
+    pub steps: i32,
+
+    /// length of one step (default is 2)
+    #[serde(default = "FlowField::default_step")]
+    pub step: f64,
+
+    /// how fast the direction changes over the area (default is 1).
+    /// with 1 the direction turns around about every 100 units.
+    #[serde(default = "FlowField::default_noise_scale")]
+    pub noise_scale: f64,
+
+    /// seed of the noise, using the same seed creates the same field.
+    /// (default is a random seed)
+    #[serde(default)]
+    pub seed: Option<u32>,
+
+    /// width of the area (default is 100)
+    #[serde(default = "FlowField::default_width")]
+    pub width: f64,
+
+    /// height of the area (default is 100)
+    #[serde(default = "FlowField::default_height")]
+    pub height: f64,
+
+    /// color from the palette to draw with
+    #[serde(default = "Color::default")]
+    pub color: Color,
+
+    /// colors to use instead of `color`, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
+    /// tags of this object which can be used to query.
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-/// draw an path
-/// it always is just the line with color.
+impl FlowField {
+    fn default_lines() -> i32 {
+        100
+    }
+    fn default_steps() -> i32 {
+        20
+    }
+    fn default_step() -> f64 {
+        2.0
+    }
+    fn default_noise_scale() -> f64 {
+        1.0
+    }
+    fn default_width() -> f64 {
+        100.0
+    }
+    fn default_height() -> f64 {
+        100.0
+    }
+}
+
+impl Rendable for FlowField {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        let mut rng = image_context.rng();
+        let seed = match self.seed {
+            Some(seed) => seed,
+            None => rng.gen(),
+        };
+        let noise = Perlin::new(seed);
+
+        let (half_width, half_height) = (self.width / 2.0, self.height / 2.0);
+        if half_width <= 0.0 || half_height <= 0.0 {
+            return;
+        }
+        let frequency = 0.01 * self.noise_scale;
+
+        self.configure_color(
+            image_context.color_by_depth(&self.color, &self.color_by_depth, depth),
+            context,
+            image_context,
+        );
+        for _ in 0..self.lines {
+            let mut x = rng.gen_range(-half_width, half_width);
+            let mut y = rng.gen_range(-half_height, half_height);
+            context.move_to(x, y);
+            for _ in 0..self.steps {
+                let direction =
+                    std::f64::consts::PI * (1.0 + noise.get([x * frequency, y * frequency]));
+                x += self.step * direction.cos();
+                y += self.step * direction.sin();
+                if x.abs() > half_width || y.abs() > half_height {
+                    break;
+                }
+                context.line_to(x, y);
+            }
+        }
+        self.stroke_and_preserve_line_width(context);
+    }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        Some(Bounds::rectangle(self.width, self.height))
+    }
+}
+
+/// draw a random path (like a `line`).
+///
+/// The path makes `steps` steps of the length `step`,
+/// after every step the direction changes randomly by up to `turn` degrees.
+/// The drawing is centered like all other objects.
 ///
 /// #Example
 ///
 /// ```json
 /// {
-///  "type": "path",
-///  "path":[
-///  { x: 0, y: 0},
-///  { x: 0, y: 0, sa: {x: 0, y:0}, sb:{x: 0, y:0} },
-///  ]
+///  "type": "random_walk",
+///  "steps": 100,
+///  "step": 4,
+///  "turn": 30,
+///  "smooth": true
 /// }
 /// ```
-#[derive(Serialize, Deserialize)]
-pub struct Line {
-    /// path to draw the
-    path: Vec<IconPoint>,
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct RandomWalk {
+    /// number of steps (default is 50)
+    #[serde(default = "RandomWalk::default_steps")]
+    pub steps: i32,
+
+    /// length of one step (default is 5)
+    #[serde(default = "RandomWalk::default_step")]
+    pub step: f64,
+
+    /// how much (in degree) the direction changes after each step (default is 180)
+    #[serde(default = "RandomWalk::default_turn")]
+    pub turn: f64,
+
+    /// how the change of direction is distributed (default is `uniform`)
+    #[serde(default)]
+    pub distribution: Distribution,
+
+    /// only walk up, down, left or right (default is false)
+    #[serde(default)]
+    pub grid: bool,
+
+    /// draw a smooth curve through the steps instead of straight lines (default is false)
+    #[serde(default)]
+    pub smooth: bool,
 
     /// color from the palette to draw with
     #[serde(default = "Color::default")]
     pub color: Color,
 
+    /// colors to use instead of `color`, one for each level of recursion,
+    /// the last one is used for deeper levels (default is empty)
+    #[serde(default)]
+    pub color_by_depth: Vec<Color>,
+
     /// tags of this object which can be used to query.
     #[serde(default)]
     pub tags: Vec<String>,
 }
 
-impl Rendable for Line {
-    fn render(&self, context: &Context, image_context: &ImageContext, _depth: i32) {
-        self.configure_color(&self.color, context, image_context);
+/// How random values are distributed.
+/// (default is `uniform`)
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub enum Distribution {
+    /// every value between the limits is equally likely
+    #[serde(rename = "uniform")]
+    #[default]
+    Uniform,
+
+    /// small values are more likely, the limit is the standard deviation
+    #[serde(rename = "normal")]
+    Normal,
+}
+
+impl Distribution {
+    /// random value around 0 using `limit` as limit
+    fn sample<R: Rng>(&self, rng: &mut R, limit: f64) -> f64 {
+        if limit <= 0.0 {
+            return 0.0;
+        }
+        match self {
+            Distribution::Uniform => rng.gen_range(-limit, limit),
+            Distribution::Normal => {
+                // box muller transformation
+                let u: f64 = 1.0 - rng.gen::<f64>();
+                let v: f64 = rng.gen();
+                limit * f64::sqrt(-2.0 * u.ln()) * f64::cos(2.0 * std::f64::consts::PI * v)
+            }
+        }
+    }
+}
+
+impl RandomWalk {
+    fn default_steps() -> i32 {
+        50
+    }
+    fn default_step() -> f64 {
+        5.0
+    }
+    fn default_turn() -> f64 {
+        180.0
+    }
 
-        let mut first = true;
-        for path in self.path.iter() {
-            if first {
-                context.move_to(path.x, path.y);
-                first = false;
+    /// walk randomly and return the centered points of the walk
+    fn walk(&self, rng: &mut StdRng) -> Vec<(f64, f64)> {
+        let (mut x, mut y) = (0.0, 0.0);
+        let mut direction: f64 = if self.grid {
+            90.0 * f64::from(rng.gen_range(0, 4))
+        } else {
+            rng.gen_range(0.0, 360.0)
+        };
+
+        let mut points = vec![(x, y)];
+        for _ in 0..self.steps {
+            x += self.step * degree_to_radian(direction).cos();
+            y += self.step * degree_to_radian(direction).sin();
+            points.push((x, y));
+
+            let turn = self.distribution.sample(rng, self.turn);
+            direction += if self.grid {
+                90.0 * (turn / 90.0).round()
             } else {
-                if path.sa.is_none() && path.sb.is_none() {
-                    context.line_to(path.x, path.y);
-                } else {
-                    let sb = if path.sb.is_some() {
-                        Point {
-                            x: path.sb.as_ref().unwrap().x,
-                            y: path.sb.as_ref().unwrap().y,
-                        }
-                    } else {
-                        Point {
-                            x: path.x,
-                            y: path.y,
-                        }
-                    };
-                    let sa = if path.sa.is_some() {
-                        Point {
-                            x: path.sa.as_ref().unwrap().x,
-                            y: path.sa.as_ref().unwrap().y,
-                        }
-                    } else {
-                        Point {
-                            x: path.x,
-                            y: path.y,
-                        }
-                    };
-                    context.curve_to(sa.x, sa.y, sb.x, sb.y, path.x, path.y);
-                };
+                turn
+            };
+        }
+
+        let (min_x, max_x, min_y, max_y) = points.iter().fold(
+            (f64::MAX, f64::MIN, f64::MAX, f64::MIN),
+            |(min_x, max_x, min_y, max_y), (x, y)| {
+                (min_x.min(*x), max_x.max(*x), min_y.min(*y), max_y.max(*y))
+            },
+        );
+        let (center_x, center_y) = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+        points
+            .iter()
+            .map(|(x, y)| (x - center_x, y - center_y))
+            .collect()
+    }
+}
+
+impl Rendable for RandomWalk {
+    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+        let points = self.walk(&mut image_context.rng());
+
+        self.configure_color(
+            image_context.color_by_depth(&self.color, &self.color_by_depth, depth),
+            context,
+            image_context,
+        );
+        context.move_to(points[0].0, points[0].1);
+        for index in 1..points.len() {
+            let (x, y) = points[index];
+            if self.smooth {
+                // catmull rom spline through all points
+                let (x0, y0) = points[index.saturating_sub(2)];
+                let (x1, y1) = points[index - 1];
+                let (x3, y3) = points[usize::min(index + 1, points.len() - 1)];
+                context.curve_to(
+                    x1 + (x - x0) / 6.0,
+                    y1 + (y - y0) / 6.0,
+                    x - (x3 - x1) / 6.0,
+                    y - (y3 - y1) / 6.0,
+                    x,
+                    y,
+                );
+            } else {
+                context.line_to(x, y);
             }
         }
         self.stroke_and_preserve_line_width(context);
     }
+
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        // the walk is centered, and the splines are close to the points
+        Some(Bounds::around(
+            (f64::from(self.steps.max(0)) / 2.0 + 1.0) * self.step,
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -661,13 +3249,42 @@ mod tests {
     use cairo::Context;
     use cairo::Format;
     use cairo::ImageSurface;
+    use rand::SeedableRng;
     use serde_json::json;
+    use serde_json::value::Value;
+
+    /// render the structure in the center of a 100 x 100 image (with a black background),
+    /// returns the image and the fill color of the palette
+    fn render_centered(input: Value) -> (ImageSurface, [u8; 3]) {
+        let structure = Structure::load_from_value(input).unwrap();
+        let image_context = ImageContext::new(&structure);
+        let surface = ImageSurface::create(Format::Rgb24, 100, 100).expect("Can't create surface");
+        {
+            let context = Context::new(&surface);
+            context.set_line_width(structure.line_size);
+            context.translate(50.0, 50.0);
+            structure.render(&context, &image_context);
+        }
+        let fill = &image_context.palette().fill_color;
+        let channel = |value: f32| (f64::from(value) * 255.0).round() as u8;
+        let fill = [channel(fill.red), channel(fill.green), channel(fill.blue)];
+        (surface, fill)
+    }
+
+    /// the color (red, green and blue) of a pixel
+    fn pixel(surface: &mut ImageSurface, x: usize, y: usize) -> [u8; 3] {
+        let stride = surface.get_stride() as usize;
+        let data = surface.get_data().unwrap();
+        let offset = y * stride + x * 4;
+        [data[offset + 2], data[offset + 1], data[offset]]
+    }
 
     #[test]
     fn grid_recursion_always_terminates() {
         // create a structure that loops for ever
         // but should stop after a while
         let input = json!({
+            "depth": 100,
             "start": {"by_name":"main"},
             "objects": {
                 "main":{
@@ -682,15 +3299,82 @@ mod tests {
         let image_context = ImageContext::new(&structure);
         let surface = ImageSurface::create(Format::Rgb24, 100, 100).expect("Can't create surface");
         let context = Context::new(&surface);
-        structure.render(&context, &image_context, 100);
+        structure.render(&context, &image_context);
         // if this function is not crashing, than all good
     }
 
+    #[test]
+    fn grid_cells_are_rotated() {
+        // the first cell is rotated by 90 degrees, the second by 180 degrees
+        let (mut surface, fill) = render_centered(json!({
+            "seed": 1,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{
+                    "type":"grid",
+                    "columns": 2,
+                    "width": 50,
+                    "random_rotation": [90],
+                    "rotation_step": 90,
+                    "query": {"by_name":"bar"}
+                },
+                "bar":{"type":"ellipse", "rx": 20, "ry": 5}
+            }
+        }));
+        assert_eq!(pixel(&mut surface, 25, 65), fill);
+        assert_eq!(pixel(&mut surface, 40, 50), [0, 0, 0]);
+        assert_eq!(pixel(&mut surface, 90, 50), fill);
+        assert_eq!(pixel(&mut surface, 75, 65), [0, 0, 0]);
+    }
+
+    #[test]
+    fn grid_cells_are_jittered_inside_of_their_bounds() {
+        let transform = CellTransform {
+            scale: 100.0,
+            random_rotation: &[],
+            rotation_step: 45.0,
+            scale_jitter: 50.0,
+            position_jitter: 10.0,
+        };
+        // rotated, scaled up to 150% and moved up to 10
+        let limit = f64::sqrt(800.0) * 1.5 + 10.0;
+        let bounds = transform.bounds(Bounds::around(20.0));
+        assert!((bounds.max_x - limit).abs() < 1e-9 && (bounds.min_y + limit).abs() < 1e-9);
+
+        let mut drawn = Vec::new();
+        for seed in 0..5 {
+            let (mut surface, _) = render_centered(json!({
+                "seed": seed,
+                "start": {"by_name":"main"},
+                "objects": {
+                    "main":{
+                        "type":"grid",
+                        "scale_jitter": 50,
+                        "position_jitter": 10,
+                        "query": {"by_name":"dot"}
+                    },
+                    "dot":{"type":"circle", "radius": 20}
+                }
+            }));
+            // the columns of the image the circle is drawn in
+            let columns: Vec<usize> = (0..100)
+                .filter(|x| pixel(&mut surface, *x, 50) != [0, 0, 0])
+                .collect();
+            let (left, right) = (columns[0], columns[columns.len() - 1]);
+            assert!(left as f64 >= 50.0 - 20.0 * 1.5 - 10.0 - 1.0);
+            assert!(right as f64 <= 50.0 + 20.0 * 1.5 + 10.0);
+            drawn.push((left, right));
+        }
+        drawn.dedup();
+        assert!(drawn.len() > 1);
+    }
+
     #[test]
     fn sun_recursion_always_terminates() {
         // create a structure that loops for ever
         // but should stop after a while
         let input = json!({
+            "depth": 100,
             "start": {"by_name":"main"},
             "objects": {
                 "main":{
@@ -706,7 +3390,568 @@ mod tests {
         let image_context = ImageContext::new(&structure);
         let surface = ImageSurface::create(Format::Rgb24, 100, 100).expect("Can't create surface");
         let context = Context::new(&surface);
-        structure.render(&context, &image_context, 100);
+        structure.render(&context, &image_context);
+        // if this function is not crashing, than all good
+    }
+
+    #[test]
+    fn too_small_objects_are_culled() {
+        let input = json!({
+            "depth": 10,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{
+                    "type":"sequence",
+                    "scale": 1,
+                    "objects": [{"type":"circle"}]
+                }
+            }
+        });
+        let mut structure = Structure::load_from_value(input).unwrap();
+        let surface = ImageSurface::create(Format::Rgb24, 100, 100).expect("Can't create surface");
+        let context = Context::new(&surface);
+
+        // 100 units are about 1.4 pixels
+        structure.min_feature_size = 1.0;
+        let image_context = ImageContext::new(&structure);
+        structure.render(&context, &image_context);
+        assert_eq!(image_context.culled(), 0);
+
+        structure.min_feature_size = 2.0;
+        let image_context = ImageContext::new(&structure);
+        structure.render(&context, &image_context);
+        assert_eq!(image_context.culled(), 1);
+        // the sequence restores the context, even when culled
+        assert_eq!(context.get_matrix().xx, 1.0);
+    }
+
+    #[test]
+    fn objects_outside_of_the_image_are_skipped() {
+        let input = json!({
+            "depth": 10,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{
+                    "type":"grid",
+                    "rows": 5,
+                    "columns": 5,
+                    "query": {"by_name":"dot"}
+                },
+                "dot":{"type":"circle", "radius": 40}
+            }
+        });
+        let structure = Structure::load_from_value(input).unwrap();
+        let surface = ImageSurface::create(Format::Rgb24, 100, 100).expect("Can't create surface");
+        let context = Context::new(&surface);
+        context.translate(50.0, 50.0);
+
+        let image_context = ImageContext::new(&structure);
+        let main = ImageContext::object_to_rendable_box(&structure.objects["main"]).unwrap();
+        assert_eq!(
+            image_context.bounds_of(*main, 10),
+            Some(Bounds::new(-240.0, -240.0, 240.0, 240.0))
+        );
+
+        // only the dot in the center is inside of the image
+        structure.render(&context, &image_context);
+        assert_eq!(image_context.off_canvas(), 24);
+    }
+
+    #[test]
+    fn polyline_measures_along_the_path() {
+        let path: Path = serde_json::from_value(json!([
+            {"x": 0, "y": 0},
+            {"x": 30, "y": 0},
+            {"x": 30, "y": 40},
+        ]))
+        .unwrap();
+        let polyline = Polyline::new(&path.segments(), false);
+        assert_eq!(polyline.length, 70.0);
+
+        let (x, y, _) = polyline.point_at(50.0).unwrap();
+        assert_eq!((x, y), (30.0, 20.0));
+
+        let closed = Polyline::new(&path.segments(), true);
+        assert_eq!(closed.length, 120.0);
+
+        let path: Path = serde_json::from_value(json!("M 0 0 h 30 v 40")).unwrap();
+        assert_eq!(Polyline::new(&path.segments(), true).length, 120.0);
+
+        let error = serde_json::from_value::<Path>(json!("M 0 0 X 5"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "invalid path data at position 7: unknown command `X`"
+        );
+    }
+
+    #[test]
+    fn lsystem_expansion_is_limited_by_depth() {
+        let lsystem: LSystem = serde_json::from_value(json!({
+            "axiom": "F",
+            "rules": {"F": "F+F"},
+            "iterations": 10,
+        }))
+        .unwrap();
+        assert_eq!(lsystem.expand(2), "F+F+F+F");
+
+        let input = json!({
+            "depth": 5,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{
+                    "type":"lsystem",
+                    "axiom":"FX",
+                    "rules": {"X": "X+FX"},
+                    "iterations": 1000,
+                    "queries":{"X": {"by_name":"main"}},
+                }
+            }
+        });
+        let mut structure = Structure::load_from_value(input).unwrap();
+        let image_context = ImageContext::new(&structure);
+        let surface = ImageSurface::create(Format::Rgb24, 100, 100).expect("Can't create surface");
+        let context = Context::new(&surface);
+        structure.render(&context, &image_context);
         // if this function is not crashing, than all good
+
+        // placed objects stop when they get too small
+        structure.min_feature_size = 1000.0;
+        let image_context = ImageContext::new(&structure);
+        structure.render(&context, &image_context);
+        assert_eq!(image_context.culled(), 1);
+    }
+
+    #[test]
+    fn lsystems_outside_of_the_image_are_skipped() {
+        let input = json!({
+            "depth": 10,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{
+                    "type":"grid",
+                    "rows": 5,
+                    "columns": 5,
+                    "query": {"by_name":"plant"}
+                },
+                "plant":{
+                    "type":"lsystem",
+                    "axiom": "FL",
+                    "step": 60,
+                    "queries": {"L": {"by_name":"leaf"}}
+                },
+                "leaf":{"type":"circle", "radius": 10}
+            }
+        });
+        let structure = Structure::load_from_value(input).unwrap();
+        let surface = ImageSurface::create(Format::Rgb24, 100, 100).expect("Can't create surface");
+        let context = Context::new(&surface);
+        context.translate(50.0, 50.0);
+
+        let image_context = ImageContext::new(&structure);
+        let plant = ImageContext::object_to_rendable_box(&structure.objects["plant"]).unwrap();
+        // the line from 30 to -30, and the leaf (in any rotation) at its end
+        let bounds = image_context.bounds_of(*plant, 10).unwrap();
+        let leaf = f64::sqrt(200.0);
+        let expected = Bounds::new(-leaf, -30.0 - leaf, leaf, 30.0);
+        for (value, expected) in bounds.corners().iter().zip(expected.corners().iter()) {
+            assert!((value.0 - expected.0).abs() < 1e-9 && (value.1 - expected.1).abs() < 1e-9);
+        }
+
+        // only the plant in the center is inside of the image
+        structure.render(&context, &image_context);
+        assert_eq!(image_context.off_canvas(), 24);
+    }
+
+    #[test]
+    fn spiral_places_objects_on_the_turns() {
+        let spiral: Spiral = serde_json::from_value(json!({
+            "query": {"by_name":"dot"},
+            "radius": 10,
+            "growth": 100,
+            "kind": "logarithmic",
+        }))
+        .unwrap();
+        assert_eq!(spiral.radius_at(2.0), 40.0);
+
+        // 4 objects on one turn : right, bottom, left and top
+        let (mut surface, fill) = render_centered(json!({
+            "seed": 1,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{
+                    "type":"spiral",
+                    "query": {"by_name":"dot"},
+                    "count": 4,
+                    "turns": 1,
+                    "radius": 10,
+                    "growth": 30,
+                },
+                "dot":{"type":"circle", "radius": 4}
+            }
+        }));
+        for (x, y) in [(60, 50), (50, 67), (25, 50), (50, 17)].iter() {
+            assert_eq!(pixel(&mut surface, *x, *y), fill);
+        }
+        assert_ne!(fill, [0, 0, 0]);
+        assert_eq!(pixel(&mut surface, 50, 50), [0, 0, 0]);
+        assert_eq!(pixel(&mut surface, 75, 50), [0, 0, 0]);
+    }
+
+    #[test]
+    fn along_path_places_objects_in_even_distances() {
+        // 3 objects on a straight line : at the start, the middle and the end
+        let (mut surface, fill) = render_centered(json!({
+            "seed": 1,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{
+                    "type":"along_path",
+                    "query": {"by_name":"dot"},
+                    "count": 3,
+                    "path": [{"x": -40, "y": 0}, {"x": 40, "y": 0}],
+                },
+                "dot":{"type":"circle", "radius": 4}
+            }
+        }));
+        for (x, y) in [(10, 50), (50, 50), (90, 50)].iter() {
+            assert_eq!(pixel(&mut surface, *x, *y), fill);
+        }
+        assert_ne!(fill, [0, 0, 0]);
+        assert_eq!(pixel(&mut surface, 30, 50), [0, 0, 0]);
+        assert_eq!(pixel(&mut surface, 70, 50), [0, 0, 0]);
+
+        // a rotated object follows the direction of a vertical path
+        let (mut surface, fill) = render_centered(json!({
+            "seed": 1,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{
+                    "type":"along_path",
+                    "query": {"by_name":"bar"},
+                    "count": 1,
+                    "rotate": true,
+                    "path": [{"x": 0, "y": -40}, {"x": 0, "y": 40}],
+                },
+                "bar":{"type":"ellipse", "rx": 20, "ry": 4}
+            }
+        }));
+        assert_eq!(pixel(&mut surface, 50, 65), fill);
+        assert_eq!(pixel(&mut surface, 65, 50), [0, 0, 0]);
+    }
+
+    #[test]
+    fn containers_and_lines_change_with_the_level() {
+        // the spiral (at level 0) places its only object in the center at half the size
+        let (mut surface, fill) = render_centered(json!({
+            "seed": 1,
+            "depth": 10,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{
+                    "type":"spiral",
+                    "query": {"by_name":"dot"},
+                    "count": 1,
+                    "radius": 0,
+                    "scale_by_depth": [50, 100],
+                },
+                "dot":{"type":"circle", "radius": 20}
+            }
+        }));
+        assert_eq!(pixel(&mut surface, 55, 50), fill);
+        assert_eq!(pixel(&mut surface, 65, 50), [0, 0, 0]);
+
+        // the line of the l-system (at level 0) is drawn with the first color
+        let (mut surface, fill) = render_centered(json!({
+            "seed": 1,
+            "depth": 10,
+            "line_size": 4,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{
+                    "type":"lsystem",
+                    "axiom": "F",
+                    "step": 40,
+                    "color": "background",
+                    "color_by_depth": ["fill"],
+                }
+            }
+        }));
+        let line = pixel(&mut surface, 50, 50);
+        for (channel, fill) in line.iter().zip(fill.iter()) {
+            assert!((i32::from(*channel) - i32::from(*fill)).abs() <= 1);
+        }
+        assert_eq!(pixel(&mut surface, 60, 50), [0, 0, 0]);
+    }
+
+    #[test]
+    fn arcs_pies_and_ellipses_draw_their_part() {
+        let render = |object: Value| {
+            render_centered(json!({
+                "seed": 1,
+                "start": {"by_name":"main"},
+                "objects": {"main": object}
+            }))
+        };
+
+        // angles grow clockwise, so 0 to 90 is the bottom right
+        let (mut surface, fill) = render(json!({"type":"pie", "radius": 40}));
+        assert_eq!(pixel(&mut surface, 70, 70), fill);
+        for (x, y) in [(30, 30), (70, 30), (30, 70)].iter() {
+            assert_eq!(pixel(&mut surface, *x, *y), [0, 0, 0]);
+        }
+
+        let (mut surface, _) = render(json!({"type":"arc", "radius": 40}));
+        assert_ne!(pixel(&mut surface, 78, 78), [0, 0, 0]);
+        assert_eq!(pixel(&mut surface, 70, 70), [0, 0, 0]);
+        assert_eq!(pixel(&mut surface, 21, 21), [0, 0, 0]);
+
+        let (mut surface, fill) = render(json!({"type":"ellipse", "rx": 40, "ry": 10}));
+        assert_eq!(pixel(&mut surface, 85, 50), fill);
+        assert_eq!(pixel(&mut surface, 50, 65), [0, 0, 0]);
+
+        let (mut surface, _) =
+            render(json!({"type":"ellipse", "rx": 40, "ry": 10, "filled": false}));
+        assert_ne!(pixel(&mut surface, 89, 50), [0, 0, 0]);
+        assert_eq!(pixel(&mut surface, 50, 50), [0, 0, 0]);
+    }
+
+    #[test]
+    fn images_can_be_tinted_and_transparent() {
+        // a red png
+        let path = std::env::temp_dir().join(format!("image-test-{}.png", std::process::id()));
+        let red = ImageSurface::create(Format::ARgb32, 4, 4).expect("Can't create surface");
+        {
+            let context = Context::new(&red);
+            context.set_source_rgb(1.0, 0.0, 0.0);
+            context.paint();
+        }
+        red.write_to_png(&mut File::create(&path).unwrap()).unwrap();
+
+        let render = |image: Value| {
+            render_centered(json!({
+                "seed": 1,
+                "start": {"by_name":"main"},
+                "objects": {"main": image}
+            }))
+        };
+        let file = path.to_str().unwrap();
+
+        let (mut surface, _) =
+            render(json!({"type":"image", "file": file, "width": 20, "height": 20}));
+        assert_eq!(pixel(&mut surface, 50, 50), [255, 0, 0]);
+        assert_eq!(pixel(&mut surface, 65, 50), [0, 0, 0]);
+
+        let (mut surface, fill) = render(json!({
+            "type":"image", "file": file, "width": 20, "height": 20, "tint": "fill", "opacity": 50
+        }));
+        let tinted = pixel(&mut surface, 50, 50);
+        for (channel, fill) in tinted.iter().zip(fill.iter()) {
+            assert!((i32::from(*channel) - i32::from(*fill) / 2).abs() <= 1);
+        }
+        assert_ne!(tinted, [0, 0, 0]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn subdivide_covers_the_whole_area() {
+        let subdivide: Subdivide = serde_json::from_value(json!({
+            "query": {"by_name":"main"},
+            "width": 300,
+            "height": 200,
+            "levels": 6,
+        }))
+        .unwrap();
+        let leaves = subdivide.leaves(&mut StdRng::seed_from_u64(0));
+        assert!(leaves.len() <= 64);
+        let area: f64 = leaves.iter().map(|leaf| leaf.width * leaf.height).sum();
+        assert!((area - 300.0 * 200.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn subdivide_levels_are_limited() {
+        let subdivide: Subdivide = serde_json::from_value(json!({
+            "query": {"by_name":"main"},
+            "levels": 40,
+            "split_chance": 100,
+        }))
+        .unwrap();
+        let leaves = subdivide.leaves(&mut StdRng::seed_from_u64(0));
+        assert_eq!(leaves.len(), 1 << Subdivide::MAX_LEVELS);
+    }
+
+    #[test]
+    fn voronoi_cells_cover_the_whole_area() {
+        let voronoi: Voronoi = serde_json::from_value(json!({
+            "points": 25,
+            "width": 300,
+            "height": 200,
+        }))
+        .unwrap();
+        let cells = voronoi.cells(&mut StdRng::seed_from_u64(0));
+        assert_eq!(cells.len(), 25);
+        let area: f64 = cells
+            .iter()
+            .map(|cell| {
+                let corners = &cell.corners;
+                (0..corners.len())
+                    .map(|index| {
+                        let (x0, y0) = corners[index];
+                        let (x1, y1) = corners[(index + 1) % corners.len()];
+                        x0 * y1 - x1 * y0
+                    })
+                    .sum::<f64>()
+                    .abs()
+                    / 2.0
+            })
+            .sum();
+        assert!((area - 300.0 * 200.0).abs() < 0.001);
+
+        // an empty area has no cells
+        let voronoi: Voronoi = serde_json::from_value(json!({"width": 0})).unwrap();
+        assert!(voronoi.cells(&mut StdRng::seed_from_u64(0)).is_empty());
+
+        let voronoi: Voronoi = serde_json::from_value(json!({"points": 1_000_000})).unwrap();
+        let cells = voronoi.cells(&mut StdRng::seed_from_u64(0));
+        assert_eq!(cells.len() as i32, Voronoi::MAX_POINTS);
+    }
+
+    #[test]
+    fn too_small_voronoi_places_the_fallback() {
+        let render = |min_feature_size: f64| {
+            render_centered(json!({
+                "seed": 1,
+                "min_feature_size": min_feature_size,
+                "start": {"by_name":"main"},
+                "objects": {
+                    "main":{
+                        "type":"voronoi",
+                        "points": 1,
+                        "stroke": false,
+                        "query": {"by_name":"dot", "fallback": {"by_name":"leaf"}}
+                    },
+                    "dot":{"type":"circle", "color": "background"},
+                    "leaf":{"type":"circle"}
+                }
+            }))
+        };
+        let (mut surface, fill) = render(0.3);
+        assert_ne!(pixel(&mut surface, 50, 50), fill);
+        let (mut surface, fill) = render(1000.0);
+        assert_eq!(pixel(&mut surface, 50, 50), fill);
+    }
+
+    #[test]
+    fn flow_fields_in_an_empty_area_draw_nothing() {
+        for (width, height) in [(0, 100), (100, -10)].iter() {
+            let (mut surface, _) = render_centered(json!({
+                "seed": 1,
+                "start": {"by_name":"main"},
+                "objects": {
+                    "main": {"type":"flow_field", "width": width, "height": height}
+                }
+            }));
+            assert_eq!(pixel(&mut surface, 50, 50), [0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn random_walk_on_grid_stays_on_grid() {
+        let random_walk: RandomWalk = serde_json::from_value(json!({
+            "steps": 200,
+            "step": 10,
+            "grid": true,
+            "distribution": "normal",
+            "turn": 90,
+        }))
+        .unwrap();
+        let points = random_walk.walk(&mut StdRng::seed_from_u64(0));
+        assert_eq!(points.len(), 201);
+        for line in points.windows(2) {
+            let (dx, dy) = (line[1].0 - line[0].0, line[1].1 - line[0].1);
+            assert!(dx.abs() < 0.001 || dy.abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn random_walk_turns_a_bit_and_stays_inside_of_its_bounds() {
+        let input = json!({
+            "seed": 3,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{"type":"random_walk", "steps": 40, "step": 2, "turn": 30}
+            }
+        });
+        let structure = Structure::load_from_value(input.clone()).unwrap();
+        let image_context = ImageContext::new(&structure);
+        let random_walk: RandomWalk = serde_json::from_value(json!({
+            "steps": 40,
+            "step": 2,
+            "turn": 30,
+        }))
+        .unwrap();
+        let bounds = random_walk.bounds(&image_context, 0).unwrap();
+        let points = random_walk.walk(&mut StdRng::seed_from_u64(3));
+        assert_eq!(points.len(), 41);
+        for (x, y) in points.iter() {
+            assert!(bounds.min_x <= *x && *x <= bounds.max_x);
+            assert!(bounds.min_y <= *y && *y <= bounds.max_y);
+        }
+
+        // every step has the same length and turns at most by `turn`
+        let directions: Vec<f64> = points
+            .windows(2)
+            .map(|line| {
+                let (dx, dy) = (line[1].0 - line[0].0, line[1].1 - line[0].1);
+                assert!((dx.hypot(dy) - 2.0).abs() < 0.001);
+                dy.atan2(dx).to_degrees()
+            })
+            .collect();
+        for pair in directions.windows(2) {
+            let turn = (pair[1] - pair[0] + 540.0) % 360.0 - 180.0;
+            assert!(turn.abs() <= 30.0);
+        }
+
+        // smoothing draws a different path through the same points
+        let (mut straight, _) = render_centered(input);
+        let (mut smooth, _) = render_centered(json!({
+            "seed": 3,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{"type":"random_walk", "steps": 40, "step": 2, "turn": 30, "smooth": true}
+            }
+        }));
+        let straight = straight.get_data().unwrap().to_vec();
+        let smooth = smooth.get_data().unwrap().to_vec();
+        assert!(straight.iter().any(|value| *value != 0));
+        assert_ne!(straight, smooth);
+    }
+
+    #[test]
+    fn icon_points_can_have_multiple_sub_paths() {
+        let path: Path = serde_json::from_value(json!([
+            {"x": -50, "y": -50},
+            {"x": 50, "y": -50},
+            {"x": 0, "y": 50, "close": true},
+            {"x": -10, "y": -30, "move_to": true},
+            {"x": 10, "y": -30},
+            {"x": 0, "y": 0, "close": true},
+        ]))
+        .unwrap();
+        assert_eq!(
+            path.segments(),
+            vec![
+                Segment::MoveTo(-50.0, -50.0),
+                Segment::LineTo(50.0, -50.0),
+                Segment::LineTo(0.0, 50.0),
+                Segment::ClosePath,
+                Segment::MoveTo(-10.0, -30.0),
+                Segment::LineTo(10.0, -30.0),
+                Segment::LineTo(0.0, 0.0),
+                Segment::ClosePath,
+            ]
+        );
     }
 }
@@ -17,8 +17,7 @@ pub struct Palette {
 impl Palette {
     /// generate a random color that is not to dark
     /// and to color less.
-    pub fn random_color() -> Rgb {
-        let mut rng = rand::thread_rng();
+    pub fn random_color<R: Rng>(rng: &mut R) -> Rgb {
         let y: f32 = rng.gen(); // generates a float between 0 and 1
         let hue: f32 = y * 360.0;
         let saturation: f32 = f32::max(rng.gen(), 0.6);
@@ -0,0 +1,473 @@
+//! paths in the format of svg path data
+//! (the `d` attribute of svg `path` elements)
+//! and how to draw them.
+
+use cairo::Context;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// A part of a path, all coordinates are absolute.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Segment {
+    /// start a new sub path at the point
+    MoveTo(f64, f64),
+    /// straight line to the point
+    LineTo(f64, f64),
+    /// cubic spline using two control points to the (last) point
+    CurveTo(f64, f64, f64, f64, f64, f64),
+    /// close the current sub path
+    ClosePath,
+}
+
+/// draw the segments to the context (without filling or stroking)
+pub fn draw(context: &Context, segments: &[Segment]) {
+    for segment in segments.iter() {
+        match segment {
+            Segment::MoveTo(x, y) => context.move_to(*x, *y),
+            Segment::LineTo(x, y) => context.line_to(*x, *y),
+            Segment::CurveTo(x1, y1, x2, y2, x, y) => context.curve_to(*x1, *y1, *x2, *y2, *x, *y),
+            Segment::ClosePath => context.close_path(),
+        }
+    }
+}
+
+/// svg path data, like `M 0 0 L 10 10 Z`.
+///
+/// These commands (and their relative lower case variants) are supported:
+/// `M`, `L`, `H`, `V`, `C`, `S`, `Q`, `T`, `A` and `Z`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PathData {
+    data: String,
+    segments: Vec<Segment>,
+}
+
+impl PathData {
+    pub fn segments(&self) -> &Vec<Segment> {
+        &self.segments
+    }
+}
+
+impl TryFrom<String> for PathData {
+    type Error = ParseError;
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let segments = parse(&data)?;
+        Ok(PathData { data, segments })
+    }
+}
+
+impl From<PathData> for String {
+    fn from(path_data: PathData) -> Self {
+        path_data.data
+    }
+}
+
+/// in json it is just the string
+impl JsonSchema for PathData {
+    fn schema_name() -> String {
+        "PathData".to_string()
+    }
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        String::json_schema(generator)
+    }
+}
+
+/// error while parsing svg path data
+#[derive(Debug)]
+pub struct ParseError {
+    message: String,
+    position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid path data at position {}: {}",
+            self.position, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// parse svg path data into absolute segments
+pub fn parse(data: &str) -> Result<Vec<Segment>, ParseError> {
+    let mut parser = Parser {
+        data: data.as_bytes(),
+        position: 0,
+    };
+    let mut segments = Vec::new();
+
+    // current point, start of the current sub path
+    // and the last control point (for `S` and `T`)
+    let (mut x, mut y) = (0.0, 0.0);
+    let (mut start_x, mut start_y) = (0.0, 0.0);
+    let mut last_cubic: Option<(f64, f64)> = None;
+    let mut last_quadratic: Option<(f64, f64)> = None;
+
+    let mut command = None;
+    loop {
+        parser.skip_separators();
+        if parser.is_done() {
+            break;
+        }
+        let next = parser.data[parser.position] as char;
+        if next.is_ascii_alphabetic() {
+            parser.position += 1;
+            command = Some(next);
+        } else if command.is_none() {
+            return Err(parser.error("expected a command"));
+        }
+        let current = command.unwrap();
+        let relative = current.is_ascii_lowercase();
+        let (offset_x, offset_y) = if relative { (x, y) } else { (0.0, 0.0) };
+
+        let mut cubic = None;
+        let mut quadratic = None;
+        let upper = current.to_ascii_uppercase();
+        match upper {
+            'M' => {
+                x = offset_x + parser.number()?;
+                y = offset_y + parser.number()?;
+                start_x = x;
+                start_y = y;
+                segments.push(Segment::MoveTo(x, y));
+                // following coordinates are lines
+                command = Some(if relative { 'l' } else { 'L' });
+            }
+            'L' => {
+                x = offset_x + parser.number()?;
+                y = offset_y + parser.number()?;
+                segments.push(Segment::LineTo(x, y));
+            }
+            'H' => {
+                x = offset_x + parser.number()?;
+                segments.push(Segment::LineTo(x, y));
+            }
+            'V' => {
+                y = offset_y + parser.number()?;
+                segments.push(Segment::LineTo(x, y));
+            }
+            'C' | 'S' => {
+                let (x1, y1) = if upper == 'C' {
+                    (offset_x + parser.number()?, offset_y + parser.number()?)
+                } else {
+                    match last_cubic {
+                        Some((cx, cy)) => (2.0 * x - cx, 2.0 * y - cy),
+                        None => (x, y),
+                    }
+                };
+                let x2 = offset_x + parser.number()?;
+                let y2 = offset_y + parser.number()?;
+                x = offset_x + parser.number()?;
+                y = offset_y + parser.number()?;
+                segments.push(Segment::CurveTo(x1, y1, x2, y2, x, y));
+                cubic = Some((x2, y2));
+            }
+            'Q' | 'T' => {
+                let (qx, qy) = if upper == 'Q' {
+                    (offset_x + parser.number()?, offset_y + parser.number()?)
+                } else {
+                    match last_quadratic {
+                        Some((cx, cy)) => (2.0 * x - cx, 2.0 * y - cy),
+                        None => (x, y),
+                    }
+                };
+                let (x0, y0) = (x, y);
+                x = offset_x + parser.number()?;
+                y = offset_y + parser.number()?;
+                segments.push(Segment::CurveTo(
+                    x0 + 2.0 / 3.0 * (qx - x0),
+                    y0 + 2.0 / 3.0 * (qy - y0),
+                    x + 2.0 / 3.0 * (qx - x),
+                    y + 2.0 / 3.0 * (qy - y),
+                    x,
+                    y,
+                ));
+                quadratic = Some((qx, qy));
+            }
+            'A' => {
+                let rx = parser.number()?;
+                let ry = parser.number()?;
+                let rotation = parser.number()?;
+                let large_arc = parser.flag()?;
+                let sweep = parser.flag()?;
+                let (x0, y0) = (x, y);
+                x = offset_x + parser.number()?;
+                y = offset_y + parser.number()?;
+                arc_to_curves(
+                    &mut segments,
+                    (x0, y0),
+                    (rx, ry),
+                    rotation,
+                    large_arc,
+                    sweep,
+                    (x, y),
+                );
+            }
+            'Z' => {
+                segments.push(Segment::ClosePath);
+                x = start_x;
+                y = start_y;
+                // `Z` takes no arguments, a new command has to follow
+                command = None;
+            }
+            _ => return Err(parser.error(&format!("unknown command `{}`", current))),
+        }
+        last_cubic = cubic;
+        last_quadratic = quadratic;
+    }
+    Ok(segments)
+}
+
+/// parse a list of numbers separated by white space or commas,
+/// like the `points` of an svg `polygon`
+pub fn parse_numbers(data: &str) -> Result<Vec<f64>, ParseError> {
+    let mut parser = Parser {
+        data: data.as_bytes(),
+        position: 0,
+    };
+    let mut numbers = Vec::new();
+    parser.skip_separators();
+    while !parser.is_done() {
+        numbers.push(parser.number()?);
+        parser.skip_separators();
+    }
+    Ok(numbers)
+}
+
+/// convert an svg elliptical arc into cubic splines.
+fn arc_to_curves(
+    segments: &mut Vec<Segment>,
+    from: (f64, f64),
+    radius: (f64, f64),
+    rotation: f64,
+    large_arc: bool,
+    sweep: bool,
+    to: (f64, f64),
+) {
+    let (x0, y0) = from;
+    let (x, y) = to;
+    let (mut rx, mut ry) = (radius.0.abs(), radius.1.abs());
+    if rx == 0.0 || ry == 0.0 {
+        segments.push(Segment::LineTo(x, y));
+        return;
+    }
+    if x0 == x && y0 == y {
+        return;
+    }
+
+    // https://www.w3.org/TR/SVG/implnote.html#ArcConversionEndpointToCenter
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let dx = (x0 - x) / 2.0;
+    let dy = (y0 - y) / 2.0;
+    let x1 = cos * dx + sin * dy;
+    let y1 = -sin * dx + cos * dy;
+
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut factor = f64::max(0.0, numerator / denominator).sqrt();
+    if large_arc == sweep {
+        factor = -factor;
+    }
+    let cx1 = factor * rx * y1 / ry;
+    let cy1 = -factor * ry * x1 / rx;
+    let cx = cos * cx1 - sin * cy1 + (x0 + x) / 2.0;
+    let cy = sin * cx1 + cos * cy1 + (y0 + y) / 2.0;
+
+    let angle =
+        |ux: f64, uy: f64, vx: f64, vy: f64| f64::atan2(ux * vy - uy * vx, ux * vx + uy * vy);
+    let start = angle(1.0, 0.0, (x1 - cx1) / rx, (y1 - cy1) / ry);
+    let mut delta = angle(
+        (x1 - cx1) / rx,
+        (y1 - cy1) / ry,
+        (-x1 - cx1) / rx,
+        (-y1 - cy1) / ry,
+    );
+    if !sweep && delta > 0.0 {
+        delta -= 2.0 * std::f64::consts::PI;
+    } else if sweep && delta < 0.0 {
+        delta += 2.0 * std::f64::consts::PI;
+    }
+
+    // split the arc in pieces of at most 90 degree
+    let pieces = (delta.abs() / std::f64::consts::FRAC_PI_2).ceil().max(1.0);
+    let step = delta / pieces;
+    let handle = 4.0 / 3.0 * (step / 4.0).tan();
+    let point = |theta: f64| {
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        (
+            cx + rx * cos * cos_theta - ry * sin * sin_theta,
+            cy + rx * sin * cos_theta + ry * cos * sin_theta,
+        )
+    };
+    let derivative = |theta: f64| {
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        (
+            -rx * cos * sin_theta - ry * sin * cos_theta,
+            -rx * sin * sin_theta + ry * cos * cos_theta,
+        )
+    };
+
+    for piece in 0..(pieces as i32) {
+        let theta0 = start + f64::from(piece) * step;
+        let theta1 = theta0 + step;
+        let (px0, py0) = point(theta0);
+        let (px1, py1) = point(theta1);
+        let (dx0, dy0) = derivative(theta0);
+        let (dx1, dy1) = derivative(theta1);
+        segments.push(Segment::CurveTo(
+            px0 + handle * dx0,
+            py0 + handle * dy0,
+            px1 - handle * dx1,
+            py1 - handle * dy1,
+            px1,
+            py1,
+        ));
+    }
+    // make sure we end exactly at the target
+    if let Some(Segment::CurveTo(_, _, _, _, last_x, last_y)) = segments.last_mut() {
+        *last_x = x;
+        *last_y = y;
+    }
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn is_done(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            position: self.position,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while !self.is_done() {
+            match self.data[self.position] {
+                b' ' | b'\t' | b'\n' | b'\r' | b',' => self.position += 1,
+                _ => break,
+            }
+        }
+    }
+
+    /// parse a flag of an arc, which is `0` or `1`
+    fn flag(&mut self) -> Result<bool, ParseError> {
+        self.skip_separators();
+        if self.is_done() {
+            return Err(self.error("expected a flag"));
+        }
+        let flag = match self.data[self.position] {
+            b'0' => false,
+            b'1' => true,
+            _ => return Err(self.error("expected a flag (0 or 1)")),
+        };
+        self.position += 1;
+        Ok(flag)
+    }
+
+    fn number(&mut self) -> Result<f64, ParseError> {
+        self.skip_separators();
+        let start = self.position;
+        let mut seen_dot = false;
+        let mut seen_exponent = false;
+        if !self.is_done() && (self.data[self.position] == b'-' || self.data[self.position] == b'+')
+        {
+            self.position += 1;
+        }
+        while !self.is_done() {
+            match self.data[self.position] {
+                b'0'..=b'9' => self.position += 1,
+                b'.' if !seen_dot && !seen_exponent => {
+                    seen_dot = true;
+                    self.position += 1;
+                }
+                b'e' | b'E' if !seen_exponent && self.position > start => {
+                    seen_exponent = true;
+                    self.position += 1;
+                    if !self.is_done()
+                        && (self.data[self.position] == b'-' || self.data[self.position] == b'+')
+                    {
+                        self.position += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+        std::str::from_utf8(&self.data[start..self.position])
+            .ok()
+            .and_then(|number| number.parse::<f64>().ok())
+            .ok_or(ParseError {
+                message: "expected a number".to_string(),
+                position: start,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parse_absolute_and_relative_commands() {
+        let segments = parse("M10,10 l 10 0 v10 H10 z m 5-5 q5 0 10 10 t 0 10").unwrap();
+        assert_eq!(
+            segments[0..5],
+            [
+                Segment::MoveTo(10.0, 10.0),
+                Segment::LineTo(20.0, 10.0),
+                Segment::LineTo(20.0, 20.0),
+                Segment::LineTo(10.0, 20.0),
+                Segment::ClosePath,
+            ]
+        );
+        assert_eq!(segments[5], Segment::MoveTo(15.0, 5.0));
+        match segments[6] {
+            Segment::CurveTo(_, _, _, _, x, y) => assert_eq!((x, y), (25.0, 15.0)),
+            _ => panic!("expected a curve"),
+        }
+        match segments[7] {
+            Segment::CurveTo(_, _, _, _, x, y) => assert_eq!((x, y), (25.0, 25.0)),
+            _ => panic!("expected a curve"),
+        }
+    }
+
+    #[test]
+    fn parse_arcs_and_compact_numbers() {
+        let segments = parse("M-5.5.5A5 5 0 1 1 4.5.5z").unwrap();
+        assert_eq!(segments[0], Segment::MoveTo(-5.5, 0.5));
+        // a half circle is split into two pieces
+        assert_eq!(segments.len(), 4);
+        match segments[2] {
+            Segment::CurveTo(_, _, _, _, x, y) => assert_eq!((x, y), (4.5, 0.5)),
+            _ => panic!("expected a curve"),
+        }
+        assert_eq!(segments[3], Segment::ClosePath);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("10 10").is_err());
+        assert!(parse("M 10").is_err());
+        assert!(parse("M 10 10 X 5").is_err());
+    }
+}
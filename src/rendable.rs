@@ -2,6 +2,7 @@
 //! also provides some functions related
 //! to rendering.
 
+use crate::bounds::Bounds;
 use crate::objects::Color;
 use crate::structure::ImageContext;
 use cairo::Context;
@@ -11,6 +12,12 @@ pub trait Rendable {
     /// render an object
     fn render(&self, context: &Context, image_context: &ImageContext, depth: i32);
 
+    /// estimate the bounds of everything the object draws (in its own coordinates),
+    /// when rendered with `depth`. Objects without bounds are always rendered.
+    fn bounds(&self, _image_context: &ImageContext, _depth: i32) -> Option<Bounds> {
+        None
+    }
+
     /// configure the color to draw with
     fn configure_color(&self, color: &Color, context: &Context, image_context: &ImageContext) {
         match &color {
@@ -33,6 +40,28 @@ pub trait Rendable {
         }
     }
 
+    /// configure the color to draw with, but transparent
+    /// (alpha is between 0 and 1)
+    fn configure_color_with_alpha(
+        &self,
+        color: &Color,
+        alpha: f64,
+        context: &Context,
+        image_context: &ImageContext,
+    ) {
+        let palette = image_context.palette();
+        let color = match &color {
+            Color::Fill => palette.fill_color,
+            Color::Background => palette.background_color,
+        };
+        context.set_source_rgba(
+            f64::from(color.red),
+            f64::from(color.green),
+            f64::from(color.blue),
+            alpha,
+        );
+    }
+
     /// a stroke function that preserves the line with
     #[inline(always)]
     fn stroke_and_preserve_line_width(&self, context: &Context) {
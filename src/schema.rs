@@ -0,0 +1,124 @@
+//! json schema of the input files, derived from the types,
+//! to get autocompletion and validation in editors.
+
+use crate::structure::Structure;
+use schemars::gen::SchemaSettings;
+use schemars::schema::{
+    InstanceType, Metadata, RootSchema, Schema, SchemaObject, SingleOrVec, SubschemaValidation,
+};
+use schemars::visit::{self, Visitor};
+
+/// the json schema of `input.json`
+pub fn schema() -> RootSchema {
+    let generator = SchemaSettings::draft07()
+        .with_visitor(Expressions)
+        .into_generator();
+    let mut root = generator.into_root_schema_for::<Structure>();
+    root.definitions
+        .insert("Expression".to_string(), expression());
+    root
+}
+
+/// a string with a `$`, which is replaced by a variable
+/// or evaluated when the structure is loaded.
+fn expression() -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        metadata: Some(Box::new(Metadata {
+            description: Some(
+                "a variable like \"$cell\" or an expression like \"$cell * 2\"".to_string(),
+            ),
+            ..Default::default()
+        })),
+        string: Some(Box::new(schemars::schema::StringValidation {
+            pattern: Some("\\$".to_string()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// every field can also be an expression,
+/// so every value (except strings) also allows one.
+#[derive(Debug, Clone)]
+struct Expressions;
+
+impl Visitor for Expressions {
+    fn visit_schema_object(&mut self, schema: &mut SchemaObject) {
+        visit::visit_schema_object(self, schema);
+        if let Some(object) = &mut schema.object {
+            object.properties.values_mut().for_each(allow_expression);
+            if let Some(additional) = &mut object.additional_properties {
+                allow_expression(additional);
+            }
+        }
+        if let Some(array) = &mut schema.array {
+            if let Some(SingleOrVec::Single(items)) = &mut array.items {
+                allow_expression(items);
+            }
+        }
+    }
+}
+
+/// replace the schema by `anyOf` the schema or an expression,
+/// the description and default stay on the outside for editors.
+fn allow_expression(schema: &mut Schema) {
+    let object = match schema {
+        Schema::Object(object) => object,
+        Schema::Bool(_) => return,
+    };
+    let is_string = object.instance_type == Some(InstanceType::String.into());
+    if is_string || object.reference.as_deref() == Some("#/definitions/Expression") {
+        return;
+    }
+    let metadata = object.metadata.take();
+    let any_of = vec![
+        Schema::Object(object.clone()),
+        Schema::new_ref("#/definitions/Expression".to_string()),
+    ];
+    *schema = SchemaObject {
+        metadata,
+        subschemas: Some(Box::new(SubschemaValidation {
+            any_of: Some(any_of),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into();
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn schema_is_derived_from_the_types() {
+        let schema = serde_json::to_value(schema()).unwrap();
+        for name in ["Object", "Query", "Choose", "Color", "Expression"].iter() {
+            assert!(
+                schema["definitions"].get(name).is_some(),
+                "{} is missing",
+                name
+            );
+        }
+        let width = &schema["properties"]["width"];
+        assert_eq!(width["default"], json!(100));
+        assert_eq!(width["anyOf"][0]["type"], json!("integer"));
+        assert_eq!(width["anyOf"][1]["$ref"], json!("#/definitions/Expression"));
+        let types: Vec<&Value> = schema["definitions"]["Object"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| &variant["properties"]["type"]["enum"][0])
+            .collect();
+        assert!(types.contains(&&json!("grid")));
+        assert!(types.contains(&&json!("random_walk")));
+        assert_eq!(
+            schema["definitions"]["Choose"]["enum"],
+            json!(["once", "every_time"])
+        );
+    }
+}
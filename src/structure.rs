@@ -1,17 +1,29 @@
-use crate::objects::Object;
+use crate::bounds::Bounds;
+use crate::expression;
+use crate::objects::{Color, Object};
 use crate::palette::Palette;
 use crate::rendable::Rendable;
+use crate::template;
+use crate::template::Template;
+use crate::validate::{Problem, Severity};
 use cairo::Context;
+use jsonnet::JsonnetVm;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use schemars::JsonSchema;
+use serde::de::{self, DeserializeOwned, Deserializer, MapAccess, Visitor};
 use serde::{Deserialize, Serialize};
 use serde_json::value::Value;
-use std::collections::HashMap;
+use serde_path_to_error::Segment;
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::fs::File;
-use std::io::BufReader;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct Structure {
     /// width of the picture
     #[serde(default = "Structure::default_width")]
@@ -25,9 +37,29 @@ pub struct Structure {
     #[serde(default)]
     pub objects: HashMap<String, Object>,
 
+    /// variables, which can be used in all fields (except in `vars`)
+    /// like `"$cell"` or in expressions like `"$cell * 2"`.
+    #[serde(default)]
+    pub vars: HashMap<String, Value>,
+
+    /// objects with parameters, which can be used by queries
+    /// like objects, but with `args`.
+    #[serde(default)]
+    pub templates: HashMap<String, Template>,
+
+    /// other files, whose objects are added to `objects`.
+    /// (relative to this file)
+    #[serde(default, alias = "imports")]
+    pub include: Vec<Include>,
+
     /// Query to find the first element to draw from
     pub start: Query,
 
+    /// query to draw instead, when the depth is reached
+    /// (or objects are too small) and the query has no `fallback`.
+    #[serde(default)]
+    pub fallback: Option<Query>,
+
     /// color scheme to use for generating palette
     #[serde(default = "Structure::default_color_scheme")]
     pub color_scheme: ColorScheme,
@@ -41,9 +73,21 @@ pub struct Structure {
     /// How many queries in a row should be called before stopping.
     #[serde(default = "Structure::default_depth")]
     pub depth: i32,
+
+    /// Objects stop being drawn when 100 units are smaller than
+    /// `min_feature_size` pixels in the image. (default is 0.3)
+    /// Use a smaller value to see finer details, or a bigger one for faster previews.
+    #[serde(default = "Structure::default_min_feature_size")]
+    pub min_feature_size: f64,
+
+    /// seed for the random number generator.
+    /// rendering the same structure with the same seed
+    /// creates the same image. (default is a random seed)
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub enum ColorScheme {
     #[serde(rename = "dark_on_bright")]
     DarkOnBright,
@@ -67,88 +111,549 @@ impl Structure {
     fn default_line_size() -> f64 {
         1.0
     }
+    fn default_min_feature_size() -> f64 {
+        0.3
+    }
+    /// load a json, yaml or toml file (decided by the extension)
     pub fn load_from_file(path: &str) -> Result<Structure, Box<dyn Error>> {
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let structure: Structure = serde_json::from_reader(reader)?;
-        return Ok(structure);
+        let structure: Structure = read_file(Path::new(path))?;
+        structure.loaded_from(Path::new(path))
     }
+    /// evaluate a jsonnet file, with external variables (`std.extVar("name")`)
+    /// and top level arguments.
+    pub fn load_from_jsonnet(
+        path: &str,
+        ext_vars: &[(String, String)],
+        tla_vars: &[(String, String)],
+    ) -> Result<Structure, Box<dyn Error>> {
+        let mut vm = JsonnetVm::new();
+        for (name, value) in ext_vars.iter() {
+            vm.ext_var(name, value);
+        }
+        for (name, value) in tla_vars.iter() {
+            vm.tla_var(name, value);
+        }
+        let json = match vm.evaluate_file(path) {
+            Ok(json) => json.to_string(),
+            Err(error) => return Err(format!("can't evaluate {} : {}", path, error).into()),
+        };
+        let structure: Structure = from_value(serde_json::from_str(&json)?)?;
+        structure.loaded_from(Path::new(path))
+    }
+    /// files are loaded relative to the current directory
     pub fn load_from_value(input: Value) -> Result<Structure, Box<dyn Error>> {
-        let structure: Structure = serde_json::from_value(input)?;
-        return Ok(structure);
+        let mut structure: Structure = from_value(input)?;
+        structure.load_files(Path::new("."), Vec::new())?;
+        Ok(structure)
+    }
+    /// load the used files relative to the file the structure was read from
+    fn loaded_from(mut self, path: &Path) -> Result<Structure, Box<dyn Error>> {
+        let directory = path.parent().unwrap_or_else(|| Path::new("."));
+        self.load_files(directory, vec![path.canonicalize()?])?;
+        Ok(self)
+    }
+    /// load the files used by objects (like svg files) and the included files.
+    /// `including` are the files which are currently loaded, to find include cycles.
+    fn load_files(
+        &mut self,
+        directory: &Path,
+        mut including: Vec<PathBuf>,
+    ) -> Result<(), Box<dyn Error>> {
+        let queries = std::iter::once(&mut self.start)
+            .chain(self.fallback.as_mut())
+            .collect();
+        template::instantiate(&self.templates, &self.vars, &mut self.objects, queries)?;
+        for object in self.objects.values_mut() {
+            object.load_files(directory)?;
+        }
+        include_files(&mut self.objects, &self.include, directory, &mut including)
+    }
+}
+
+/// A file to include, either just the file name
+/// or the file name and a prefix which is added
+/// to the names of all objects in the file.
+///
+/// # Example
+///
+/// ```json
+/// "include": [
+///   "shapes.json",
+///   { "file": "motifs/flowers.json", "prefix": "flowers." }
+/// ]
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Include {
+    File(String),
+    Prefixed {
+        file: String,
+        #[serde(default)]
+        prefix: String,
+    },
+}
+
+impl Include {
+    fn file(&self) -> &str {
+        match self {
+            Include::File(file) => file,
+            Include::Prefixed { file, prefix: _ } => file,
+        }
+    }
+    fn prefix(&self) -> &str {
+        match self {
+            Include::File(_) => "",
+            Include::Prefixed { file: _, prefix } => prefix,
+        }
+    }
+}
+
+/// An included file, only the objects (and its includes) are used.
+/// Its templates and variables can only be used in the file itself.
+#[derive(Deserialize)]
+struct Library {
+    #[serde(default)]
+    objects: HashMap<String, Object>,
+
+    #[serde(default)]
+    vars: HashMap<String, Value>,
+
+    #[serde(default)]
+    templates: HashMap<String, Template>,
+
+    #[serde(default, alias = "imports")]
+    include: Vec<Include>,
+}
+
+/// read a json, yaml (`.yaml` or `.yml`) or toml (`.toml`) file,
+/// decided by the extension (default is json).
+fn read_file<T: DeserializeOwned>(path: &Path) -> Result<T, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let value: Value = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml") | Some("yml") => serde_yaml::from_str(&text)?,
+        Some("toml") => toml::from_str(&text)?,
+        _ => serde_json::from_str(&text)?,
+    };
+    from_value(value)
+}
+
+/// evaluate the variables and expressions, before reading the value
+fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, Box<dyn Error>> {
+    let value = expression::resolve_file(value)?;
+    serde_path_to_error::deserialize(&value).map_err(|error| {
+        let (path, message) = locate_error(&value, error);
+        Problem {
+            severity: Severity::Error,
+            path,
+            message,
+        }
+        .into()
+    })
+}
+
+/// the path and message of the error, errors in objects end at the object
+/// so they are followed into the object (and the objects in it).
+fn locate_error(
+    value: &Value,
+    error: serde_path_to_error::Error<serde_json::Error>,
+) -> (String, String) {
+    let mut path = String::new();
+    let mut error = error;
+    let mut value = Some(value);
+    loop {
+        for segment in error.path().iter() {
+            value = value.and_then(|value| match segment {
+                Segment::Seq { index } => value.get(index),
+                Segment::Map { key } => value.get(key),
+                _ => None,
+            });
+            match segment {
+                Segment::Seq { .. } => path.push_str(&segment.to_string()),
+                _ if path.is_empty() => path.push_str(&segment.to_string()),
+                _ => path.push_str(&format!(".{}", segment)),
+            }
+        }
+        match value.and_then(Object::field_error) {
+            Some(field_error) if field_error.path().iter().next().is_some() => error = field_error,
+            _ => return (path, error.into_inner().to_string()),
+        }
+    }
+}
+
+/// add the objects of the included files (and the files they include) to `objects`.
+fn include_files(
+    objects: &mut HashMap<String, Object>,
+    includes: &[Include],
+    directory: &Path,
+    including: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    for include in includes.iter() {
+        let path = directory.join(include.file());
+        let canonical = path
+            .canonicalize()
+            .map_err(|error| format!("can't include {} : {}", path.display(), error))?;
+        if including.contains(&canonical) {
+            let cycle: Vec<String> = including
+                .iter()
+                .chain(std::iter::once(&canonical))
+                .map(|file| file.display().to_string())
+                .collect();
+            return Err(format!("include cycle : {}", cycle.join(" -> ")).into());
+        }
+
+        let mut library: Library = read_file(&path)
+            .map_err(|error| format!("can't include {} : {}", path.display(), error))?;
+        let library_directory = path.parent().unwrap_or_else(|| Path::new("."));
+        template::instantiate(
+            &library.templates,
+            &library.vars,
+            &mut library.objects,
+            Vec::new(),
+        )?;
+        for object in library.objects.values_mut() {
+            object.load_files(library_directory)?;
+        }
+        including.push(canonical);
+        include_files(
+            &mut library.objects,
+            &library.include,
+            library_directory,
+            including,
+        )?;
+        including.pop();
+
+        // names used in queries of the included objects
+        // refer to objects of the included file
+        let prefix = include.prefix();
+        let names: HashSet<String> = library.objects.keys().cloned().collect();
+        let mut library_objects: Vec<(String, Object)> = library.objects.into_iter().collect();
+        library_objects.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, mut object) in library_objects {
+            if !prefix.is_empty() {
+                for query in object.queries_mut() {
+                    query.prefix_names(prefix, &names);
+                }
+            }
+            let name = format!("{}{}", prefix, name);
+            if objects.contains_key(&name) {
+                return Err(format!(
+                    "object \"{}\" included from {} is already defined",
+                    name,
+                    path.display()
+                )
+                .into());
+            }
+            objects.insert(name, object);
+        }
     }
+    Ok(())
 }
 
-impl Rendable for Structure {
-    fn render(&self, context: &Context, image_context: &ImageContext, depth: i32) {
+impl Structure {
+    /// render the structure, starting with the depth of the image context,
+    /// which is also used to find the level of recursion of every object.
+    pub fn render(&self, context: &Context, image_context: &ImageContext) {
+        let depth = image_context.depth;
         let rendable = image_context
             .get_element_from_query(&self.start, depth)
             .next();
-        if rendable.is_some() {
-            rendable.unwrap().render(&context, image_context, depth);
+        if let Some(rendable) = rendable {
+            rendable.render(context, image_context, depth);
         }
     }
 }
 
 // -------
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, JsonSchema)]
 #[serde(untagged)]
 pub enum Query {
     ByName {
         by_name: String,
+        /// arguments, if `by_name` is a template
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        args: HashMap<String, Value>,
         #[serde(default)]
         choose: Choose,
+        /// query to use instead, when the depth is reached
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fallback: Option<Box<Query>>,
     },
     OneOfNames {
         one_of_names: Vec<String>,
         #[serde(default)]
         choose: Choose,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fallback: Option<Box<Query>>,
     },
     ByTag {
         by_tag: Vec<String>,
         #[serde(default)]
         choose: Choose,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fallback: Option<Box<Query>>,
+    },
+    /// use `query` until the object using the query is at level `until_level`,
+    /// and `then` for this and deeper levels.
+    ByLevel {
+        until_level: i32,
+        query: Box<Query>,
+        then: Box<Query>,
     },
 }
 
+/// not derived (untagged), to name the missing key
+/// and to keep the path of errors in the query.
+impl<'de> Deserialize<'de> for Query {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Query, D::Error> {
+        deserializer.deserialize_map(QueryVisitor)
+    }
+}
+
+struct QueryVisitor;
+
+impl QueryVisitor {
+    const FIELDS: &'static [&'static str] = &[
+        "by_name",
+        "one_of_names",
+        "by_tag",
+        "until_level",
+        "args",
+        "choose",
+        "fallback",
+        "query",
+        "then",
+    ];
+}
+
+impl<'de> Visitor<'de> for QueryVisitor {
+    type Value = Query;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a query with by_name, one_of_names, by_tag or until_level")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Query, A::Error> {
+        let (mut by_name, mut one_of_names, mut by_tag, mut until_level) = (None, None, None, None);
+        let (mut args, mut choose, mut fallback, mut query, mut then) =
+            (None, None, None, None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "by_name" => by_name = Some(map.next_value()?),
+                "one_of_names" => one_of_names = Some(map.next_value()?),
+                "by_tag" => by_tag = Some(map.next_value()?),
+                "until_level" => until_level = Some(map.next_value()?),
+                "args" => args = Some(map.next_value()?),
+                "choose" => choose = Some(map.next_value()?),
+                "fallback" => fallback = map.next_value()?,
+                "query" => query = Some(map.next_value()?),
+                "then" => then = Some(map.next_value()?),
+                _ => return Err(de::Error::unknown_field(&key, QueryVisitor::FIELDS)),
+            }
+        }
+        // keys of another kind of query (like `args` of `by_tag`) are unknown too
+        let given = [
+            ("args", args.is_some()),
+            ("choose", choose.is_some()),
+            ("fallback", fallback.is_some()),
+            ("query", query.is_some()),
+            ("then", then.is_some()),
+        ];
+        let only = |expected: &'static [&'static str]| -> Result<(), A::Error> {
+            match given
+                .iter()
+                .find(|(key, given)| *given && !expected.contains(key))
+            {
+                Some((key, _)) => Err(de::Error::unknown_field(key, expected)),
+                None => Ok(()),
+            }
+        };
+        let choose = choose.unwrap_or_default();
+        match (by_name, one_of_names, by_tag, until_level) {
+            (Some(by_name), None, None, None) => {
+                only(&["by_name", "args", "choose", "fallback"])?;
+                Ok(Query::ByName {
+                    by_name,
+                    args: args.unwrap_or_default(),
+                    choose,
+                    fallback,
+                })
+            }
+            (None, Some(one_of_names), None, None) => {
+                only(&["one_of_names", "choose", "fallback"])?;
+                Ok(Query::OneOfNames {
+                    one_of_names,
+                    choose,
+                    fallback,
+                })
+            }
+            (None, None, Some(by_tag), None) => {
+                only(&["by_tag", "choose", "fallback"])?;
+                Ok(Query::ByTag {
+                    by_tag,
+                    choose,
+                    fallback,
+                })
+            }
+            (None, None, None, Some(until_level)) => {
+                only(&["until_level", "query", "then"])?;
+                Ok(Query::ByLevel {
+                    until_level,
+                    query: query.ok_or_else(|| de::Error::missing_field("query"))?,
+                    then: then.ok_or_else(|| de::Error::missing_field("then"))?,
+                })
+            }
+            (None, None, None, None) => Err(de::Error::custom(
+                "a query needs by_name, one_of_names, by_tag or until_level",
+            )),
+            _ => Err(de::Error::custom(
+                "a query can only have one of by_name, one_of_names, by_tag or until_level",
+            )),
+        }
+    }
+}
+
 impl Query {
+    /// add the prefix to all object names of the query, which are in `names`
+    pub fn prefix_names(&mut self, prefix: &str, names: &HashSet<String>) {
+        if let Some(fallback) = self.fallback_mut() {
+            fallback.prefix_names(prefix, names);
+        }
+        let prefixed = |name: &mut String| {
+            if names.contains(name) {
+                *name = format!("{}{}", prefix, name);
+            }
+        };
+        match self {
+            Query::ByName {
+                by_name,
+                args: _,
+                choose: _,
+                fallback: _,
+            } => prefixed(by_name),
+            Query::OneOfNames {
+                one_of_names,
+                choose: _,
+                fallback: _,
+            } => one_of_names.iter_mut().for_each(prefixed),
+            Query::ByTag {
+                by_tag: _,
+                choose: _,
+                fallback: _,
+            } => (),
+            Query::ByLevel {
+                until_level: _,
+                query,
+                then,
+            } => {
+                query.prefix_names(prefix, names);
+                then.prefix_names(prefix, names);
+            }
+        }
+    }
+
+    /// the query to use instead, when the depth is reached
+    pub fn fallback(&self) -> Option<&Query> {
+        match self {
+            Query::ByName { fallback, .. }
+            | Query::OneOfNames { fallback, .. }
+            | Query::ByTag { fallback, .. } => fallback.as_deref(),
+            Query::ByLevel { .. } => None,
+        }
+    }
+
+    pub fn fallback_mut(&mut self) -> Option<&mut Query> {
+        match self {
+            Query::ByName { fallback, .. }
+            | Query::OneOfNames { fallback, .. }
+            | Query::ByTag { fallback, .. } => fallback.as_deref_mut(),
+            Query::ByLevel { .. } => None,
+        }
+    }
+
+    /// the query to use for an object at the level
+    pub fn for_level(&self, level: i32) -> &Query {
+        match self {
+            Query::ByLevel {
+                until_level,
+                query,
+                then,
+            } => {
+                if level < *until_level {
+                    query.for_level(level)
+                } else {
+                    then.for_level(level)
+                }
+            }
+            _ => self,
+        }
+    }
+
     pub fn get_choose(&self) -> &Choose {
         match self {
-            Query::ByName { by_name: _, choose } => choose,
+            Query::ByName {
+                by_name: _,
+                args: _,
+                choose,
+                fallback: _,
+            } => choose,
             Query::OneOfNames {
                 one_of_names: _,
                 choose,
+                fallback: _,
+            } => choose,
+            Query::ByTag {
+                by_tag: _,
+                choose,
+                fallback: _,
             } => choose,
-            Query::ByTag { by_tag: _, choose } => choose,
+            Query::ByLevel {
+                until_level: _,
+                query,
+                then: _,
+            } => query.get_choose(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Copy, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Copy, Clone, Default)]
 pub enum Choose {
     #[serde(rename = "once")]
     Once,
     #[serde(rename = "every_time")]
+    #[default]
     EveryTime,
 }
 
-impl Default for Choose {
-    fn default() -> Self {
-        Choose::EveryTime
-    }
-}
-
 pub struct ImageContext<'a> {
     pub objects: &'a HashMap<String, Object>,
     pub tags: HashMap<&'a String, Vec<&'a Object>>,
     pub palette: Palette,
+    /// the seed used to initialize the random number generator
+    pub seed: u64,
+    /// the depth the rendering starts with
+    pub depth: i32,
+    /// query to use when the depth is reached and the query has no fallback
+    pub fallback: Option<&'a Query>,
+    /// objects are too small when 100 units are smaller than this (in pixels)
+    pub min_feature_size: f64,
+    /// how often rendering stopped, because objects were too small
+    culled: Cell<usize>,
+    /// how often objects were skipped, because they were outside of the image
+    off_canvas: Cell<usize>,
+    /// estimated bounds of objects (by address and depth)
+    bounds: RefCell<HashMap<(usize, i32), Option<Bounds>>>,
+    rng: RefCell<StdRng>,
 }
 
 impl ImageContext<'_> {
-    pub fn new(structure: &Structure) -> ImageContext {
+    pub fn new(structure: &Structure) -> ImageContext<'_> {
+        // sort by name, so the same seed always leads to the same image
+        let mut names: Vec<&String> = structure.objects.keys().collect();
+        names.sort();
+
         let mut tags_map: HashMap<&String, Vec<&Object>> = HashMap::new();
-        for object in structure.objects.values() {
+        for object in names.iter().map(|name| &structure.objects[*name]) {
             let tags = object.get_tags();
             for tag in tags.iter() {
                 match tags_map.get_mut(tag) {
@@ -162,60 +667,275 @@ impl ImageContext<'_> {
             }
         }
 
+        let seed = match structure.seed {
+            Some(seed) => seed,
+            None => rand::thread_rng().gen(),
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+
         let palette = match &structure.color_scheme {
-            ColorScheme::DarkOnBright => Palette::dark_on_bright(Palette::random_color()),
-            ColorScheme::BrightOnDark => Palette::bright_on_dark(Palette::random_color()),
+            ColorScheme::DarkOnBright => Palette::dark_on_bright(Palette::random_color(&mut rng)),
+            ColorScheme::BrightOnDark => Palette::bright_on_dark(Palette::random_color(&mut rng)),
         };
 
         ImageContext {
             objects: &structure.objects,
             tags: tags_map,
-            palette: palette,
+            palette,
+            seed,
+            depth: structure.depth,
+            fallback: structure.fallback.as_ref(),
+            min_feature_size: structure.min_feature_size,
+            culled: Cell::new(0),
+            off_canvas: Cell::new(0),
+            bounds: RefCell::new(HashMap::new()),
+            rng: RefCell::new(rng),
         }
     }
 
-    fn object_to_rendable_box(object: &Object) -> Option<Rc<&dyn Rendable>> {
+    pub fn object_to_rendable_box(object: &Object) -> Option<Rc<&dyn Rendable>> {
         match object {
             Object::Circle(element) => Some(Rc::new(element)),
+            Object::Arc(element) => Some(Rc::new(element)),
+            Object::Pie(element) => Some(Rc::new(element)),
+            Object::Ellipse(element) => Some(Rc::new(element)),
             Object::Grid(element) => Some(Rc::new(element)),
             Object::Icon(element) => Some(Rc::new(element)),
             Object::Line(element) => Some(Rc::new(element)),
+            Object::Svg(element) => Some(Rc::new(element)),
+            Object::Image(element) => Some(Rc::new(element)),
             Object::Ring(element) => Some(Rc::new(element)),
             Object::Sequence(element) => Some(Rc::new(element)),
             Object::Seq(element) => Some(Rc::new(element)),
             Object::Sun(element) => Some(Rc::new(element)),
+            Object::AlongPath(element) => Some(Rc::new(element)),
+            Object::Spiral(element) => Some(Rc::new(element)),
+            Object::LSystem(element) => Some(Rc::new(element)),
+            Object::Subdivide(element) => Some(Rc::new(element)),
+            Object::Voronoi(element) => Some(Rc::new(element)),
+            Object::FlowField(element) => Some(Rc::new(element)),
+            Object::RandomWalk(element) => Some(Rc::new(element)),
         }
     }
 
-    pub fn get_element_from_query<'a>(&'a self, query: &'a Query, depth: i32) -> QueryResult {
+    pub fn get_element_from_query<'a>(&'a self, query: &'a Query, depth: i32) -> QueryResult<'a> {
+        let query = query.for_level(self.level(depth));
+        // when the depth is reached only the fallback is used
+        let fallback = match depth {
+            0 => self.fallback_of(query),
+            _ => None,
+        };
+        let query = fallback.unwrap_or(query);
         // if to deep just stop with the elements
-        if depth < 1 {
+        if depth < 0 || (depth == 0 && fallback.is_none()) {
             QueryResult {
-                objects: &self.objects,
+                objects: self.objects,
                 tags: &self.tags,
-                query: query,
+                rng: &self.rng,
+                query,
                 current_item: CurrentItem::Nothing,
                 is_dead_end: true,
             }
         } else {
             QueryResult {
-                objects: &self.objects,
+                objects: self.objects,
                 tags: &self.tags,
-                query: query,
+                rng: &self.rng,
+                query,
                 current_item: CurrentItem::Uninitalized,
                 is_dead_end: false,
             }
         }
     }
 
+    /// the query to use instead of `query`, when the depth is reached
+    fn fallback_of<'q>(&'q self, query: &'q Query) -> Option<&'q Query> {
+        query
+            .fallback()
+            .or(self.fallback)
+            .map(|fallback| fallback.for_level(self.level(0)))
+    }
+
+    /// is 100 units smaller than `min_feature_size` pixels in the image.
+    /// Every time this is true, it is counted as culled.
+    pub fn is_too_small(&self, context: &Context) -> bool {
+        let (x0, y0) = context.user_to_device_distance(100.0, 100.0);
+        let (x1, y1) = context.user_to_device_distance(0.0, 0.0);
+        let too_small = f64::sqrt((x1 - x0).powi(2) + (y1 - y0).powi(2)) < self.min_feature_size;
+        if too_small {
+            self.culled.set(self.culled.get() + 1);
+        }
+        too_small
+    }
+
+    /// how often rendering stopped (or only the fallback was drawn),
+    /// because objects were too small
+    pub fn culled(&self) -> usize {
+        self.culled.get()
+    }
+
+    /// Is the object (rendered with `depth`) inside of the image,
+    /// using the current transformation.
+    /// Every time this is false, it is counted as off canvas.
+    pub fn is_visible(&self, context: &Context, rendable: &dyn Rendable, depth: i32) -> bool {
+        let bounds = match self.bounds_of(rendable, depth) {
+            Some(bounds) => bounds,
+            None => return true,
+        };
+        // lines are drawn with the line width in pixels (and anti aliasing)
+        let device = Bounds::of_points(
+            bounds
+                .corners()
+                .into_iter()
+                .map(|(x, y)| context.user_to_device(x, y)),
+        )
+        .expand(context.get_line_width() + 1.0);
+
+        context.save();
+        context.identity_matrix();
+        let (x1, y1, x2, y2) = context.clip_extents();
+        context.restore();
+
+        let visible = device.intersects(&Bounds::new(x1, y1, x2, y2));
+        if !visible {
+            self.off_canvas.set(self.off_canvas.get() + 1);
+        }
+        visible
+    }
+
+    /// how often objects were skipped, because they were outside of the image
+    pub fn off_canvas(&self) -> usize {
+        self.off_canvas.get()
+    }
+
+    /// The estimated bounds of the object rendered with `depth`
+    /// (see `Rendable::bounds`), computed once for every object and depth.
+    pub fn bounds_of(&self, rendable: &dyn Rendable, depth: i32) -> Option<Bounds> {
+        let key = (rendable as *const dyn Rendable as *const u8 as usize, depth);
+        if let Some(bounds) = self.bounds.borrow().get(&key) {
+            return *bounds;
+        }
+        let bounds = rendable.bounds(self, depth);
+        self.bounds.borrow_mut().insert(key, bounds);
+        bounds
+    }
+
+    /// The bounds of all objects a container rendered with `depth` can get from the query,
+    /// each placed by `place` (which gets the depth the query is used with).
+    /// This includes the fallback, which is used when objects get too small.
+    pub fn query_bounds<F: Fn(Bounds, i32) -> Bounds>(
+        &self,
+        query: &Query,
+        depth: i32,
+        place: F,
+    ) -> Option<Bounds> {
+        let mut depths = vec![depth];
+        if depth > 0 && self.fallback_of(query.for_level(self.level(0))).is_some() {
+            depths.push(0);
+        }
+
+        let mut bounds = Bounds::empty();
+        for depth in depths {
+            // the same query get_element_from_query uses
+            let query = query.for_level(self.level(depth));
+            let query = match depth {
+                0 => match self.fallback_of(query) {
+                    Some(fallback) => fallback,
+                    None => continue,
+                },
+                depth if depth < 0 => continue,
+                _ => query,
+            };
+            let mut found = Bounds::empty();
+            for object in self.candidates(query) {
+                if let Some(rendable) = ImageContext::object_to_rendable_box(object) {
+                    found = found.union(&self.bounds_of(*rendable, depth - 1)?);
+                }
+            }
+            bounds = bounds.union(&place(found, depth));
+        }
+        Some(bounds)
+    }
+
+    /// all objects the query can find
+    fn candidates(&self, query: &Query) -> Vec<&Object> {
+        match query {
+            Query::ByName { by_name, .. } => self.objects.get(by_name).into_iter().collect(),
+            Query::OneOfNames { one_of_names, .. } => one_of_names
+                .iter()
+                .filter_map(|name| self.objects.get(name))
+                .collect(),
+            Query::ByTag { by_tag, .. } => by_tag
+                .iter()
+                .filter_map(|tag| self.tags.get(tag))
+                .flatten()
+                .cloned()
+                .collect(),
+            Query::ByLevel { .. } => Vec::new(),
+        }
+    }
+
+    /// The depth to use the query with, at the current scale.
+    /// When objects get too small, only the fallback is drawn
+    /// (like the depth is reached). None if nothing should be drawn.
+    pub fn depth_for_scale(&self, context: &Context, query: &Query, depth: i32) -> Option<i32> {
+        if !self.is_too_small(context) {
+            return Some(depth);
+        }
+        if depth < 0 || self.fallback_of(query.for_level(self.level(0))).is_none() {
+            return None;
+        }
+        Some(0)
+    }
+
+    /// level of recursion of an object rendered with `depth`,
+    /// the start object is at level 0, the objects it queries at level 1, ...
+    pub fn level(&self, depth: i32) -> i32 {
+        self.depth - depth
+    }
+
+    /// the color for the level of recursion,
+    /// the last one is used for deeper levels (default is `color`)
+    pub fn color_by_depth<'c>(
+        &self,
+        color: &'c Color,
+        by_depth: &'c [Color],
+        depth: i32,
+    ) -> &'c Color {
+        let level = self.level(depth).max(0) as usize;
+        by_depth
+            .get(level)
+            .or_else(|| by_depth.last())
+            .unwrap_or(color)
+    }
+
+    /// the scale (in percent) for the level of recursion,
+    /// the last one is used for deeper levels (default is 100)
+    pub fn scale_by_depth(&self, by_depth: &[f64], depth: i32) -> f64 {
+        let level = self.level(depth).max(0) as usize;
+        by_depth
+            .get(level)
+            .or_else(|| by_depth.last())
+            .cloned()
+            .unwrap_or(100.0)
+    }
+
     pub fn palette(&self) -> &Palette {
         &self.palette
     }
+
+    /// the random number generator every random decision should use,
+    /// so the image can be recreated using the same seed.
+    /// Don't hold it while rendering other objects.
+    pub fn rng(&self) -> RefMut<'_, StdRng> {
+        self.rng.borrow_mut()
+    }
 }
 
 pub struct QueryResult<'a> {
     objects: &'a HashMap<String, Object>,
     tags: &'a HashMap<&'a String, Vec<&'a Object>>,
+    rng: &'a RefCell<StdRng>,
     query: &'a Query,
     current_item: CurrentItem<'a>,
     is_dead_end: bool,
@@ -229,10 +949,13 @@ pub enum CurrentItem<'a> {
 
 impl<'a> QueryResult<'a> {
     pub fn query_next(&self) -> Option<Rc<&'a dyn Rendable>> {
+        let mut rng = self.rng.borrow_mut();
         match &self.query {
             Query::ByName {
                 by_name: name,
+                args: _,
                 choose: _,
+                fallback: _,
             } => match self.objects.get(name) {
                 None => None,
                 Some(found) => ImageContext::object_to_rendable_box(found),
@@ -240,7 +963,8 @@ impl<'a> QueryResult<'a> {
             Query::OneOfNames {
                 one_of_names,
                 choose: _,
-            } => match one_of_names.choose(&mut rand::thread_rng()) {
+                fallback: _,
+            } => match one_of_names.choose(&mut *rng) {
                 None => None,
                 Some(name) => match self.objects.get(name) {
                     None => None,
@@ -250,16 +974,19 @@ impl<'a> QueryResult<'a> {
             Query::ByTag {
                 by_tag: tags,
                 choose: _,
-            } => match tags.choose(&mut rand::thread_rng()) {
+                fallback: _,
+            } => match tags.choose(&mut *rng) {
                 None => None,
                 Some(tag) => match self.tags.get(tag) {
                     None => None,
-                    Some(objects) => match objects.choose(&mut rand::thread_rng()) {
+                    Some(objects) => match objects.choose(&mut *rng) {
                         None => None,
                         Some(object) => ImageContext::object_to_rendable_box(object),
                     },
                 },
             },
+            // already replaced in get_element_from_query
+            Query::ByLevel { .. } => None,
         }
     }
 }
@@ -289,3 +1016,298 @@ impl<'a> Iterator for QueryResult<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn same_seed_creates_same_palette() {
+        let input = json!({
+            "start": {"by_name":"main"},
+            "seed": 23,
+        });
+        let structure = Structure::load_from_value(input).unwrap();
+        let first = ImageContext::new(&structure);
+        let second = ImageContext::new(&structure);
+        assert_eq!(first.seed, 23);
+        assert_eq!(first.palette.fill_color, second.palette.fill_color);
+        assert_eq!(
+            first.palette.background_color,
+            second.palette.background_color
+        );
+        assert_eq!(first.rng().gen::<u64>(), second.rng().gen::<u64>());
+    }
+
+    #[test]
+    fn load_errors_have_a_path() {
+        let input = json!({
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{"type":"grid", "query": {"choose": "once"}}
+            }
+        });
+        let error = Structure::load_from_value(input).err().unwrap();
+        let problem = error.downcast_ref::<Problem>().unwrap();
+        assert_eq!(problem.path, "objects.main.query");
+        assert_eq!(
+            problem.message,
+            "a query needs by_name, one_of_names, by_tag or until_level"
+        );
+
+        // errors in objects of a sequence
+        let input = json!({
+            "start": {"by_name":"main"},
+            "objects": {
+                "main":{"type":"sequence", "objects": [
+                    {"type":"circle"},
+                    {"type":"grid", "query": {"by_name": "main", "choose": "twice"}}
+                ]}
+            }
+        });
+        let error = Structure::load_from_value(input).err().unwrap();
+        let problem = error.downcast_ref::<Problem>().unwrap();
+        assert_eq!(problem.path, "objects.main.objects[1].query.choose");
+        assert!(problem.message.starts_with("unknown variant `twice`"));
+
+        let input = json!({"start": {"by_name":"main", "by_tag": ["dot"]}});
+        let error = Structure::load_from_value(input).err().unwrap();
+        let problem = error.downcast_ref::<Problem>().unwrap();
+        assert_eq!(problem.path, "start");
+
+        // unknown keys, and keys of other kinds of queries
+        let input = json!({"start": {"by_nmae":"main"}});
+        let error = Structure::load_from_value(input).err().unwrap();
+        let problem = error.downcast_ref::<Problem>().unwrap();
+        assert_eq!(problem.path, "start");
+        assert!(problem.message.starts_with("unknown field `by_nmae`"));
+
+        let input = json!({"start": {"by_tag": ["dot"], "args": {"size": 2}}});
+        let error = Structure::load_from_value(input).err().unwrap();
+        let problem = error.downcast_ref::<Problem>().unwrap();
+        assert_eq!(
+            problem.message,
+            "unknown field `args`, expected one of `by_tag`, `choose`, `fallback`"
+        );
+    }
+
+    #[test]
+    fn parameters_by_level_of_recursion() {
+        let input = json!({
+            "depth": 5,
+            "start": {
+                "until_level": 2,
+                "query": {"by_name": "branch"},
+                "then": {"by_name": "leaf"}
+            },
+        });
+        let structure = Structure::load_from_value(input).unwrap();
+        let image_context = ImageContext::new(&structure);
+        assert_eq!(image_context.level(5), 0);
+        assert_eq!(image_context.level(3), 2);
+
+        let name = |query: &Query| match query {
+            Query::ByName { by_name, .. } => by_name.clone(),
+            _ => panic!("not resolved"),
+        };
+        assert_eq!(name(structure.start.for_level(1)), "branch");
+        assert_eq!(name(structure.start.for_level(2)), "leaf");
+        assert_eq!(name(structure.start.for_level(7)), "leaf");
+
+        let colors = vec![Color::Fill, Color::Background];
+        assert!(matches!(
+            image_context.color_by_depth(&Color::Background, &colors, 5),
+            Color::Fill
+        ));
+        assert!(matches!(
+            image_context.color_by_depth(&Color::Fill, &colors, 2),
+            Color::Background
+        ));
+        // like the scale, the last color is used for deeper levels
+        assert!(matches!(
+            image_context.color_by_depth(&Color::Fill, &colors, 3),
+            Color::Background
+        ));
+        assert!(matches!(
+            image_context.color_by_depth(&Color::Background, &[], 2),
+            Color::Background
+        ));
+
+        assert_eq!(image_context.scale_by_depth(&[100.0, 50.0], 4), 50.0);
+        assert_eq!(image_context.scale_by_depth(&[100.0, 50.0], 1), 50.0);
+        assert_eq!(image_context.scale_by_depth(&[], 1), 100.0);
+    }
+
+    #[test]
+    fn use_fallback_when_depth_is_reached() {
+        let input = json!({
+            "depth": 3,
+            "start": {"by_name": "main", "fallback": {"by_name": "leaf"}},
+            "fallback": {"by_name": "dot"},
+            "objects": {
+                "main": {"type": "circle"},
+                "leaf": {"type": "circle"},
+                "dot": {"type": "circle"}
+            }
+        });
+        let structure = Structure::load_from_value(input).unwrap();
+        let image_context = ImageContext::new(&structure);
+        let used = |query: &Query, depth: i32| {
+            let result = image_context.get_element_from_query(query, depth);
+            match (result.is_dead_end, result.query) {
+                (false, Query::ByName { by_name, .. }) => Some(by_name.clone()),
+                _ => None,
+            }
+        };
+        let without_fallback = Query::ByTag {
+            by_tag: vec!["tag".to_string()],
+            choose: Choose::default(),
+            fallback: None,
+        };
+
+        assert_eq!(used(&structure.start, 1), Some("main".to_string()));
+        assert_eq!(used(&structure.start, 0), Some("leaf".to_string()));
+        assert_eq!(used(&structure.start, -1), None);
+        assert_eq!(used(&without_fallback, 0), Some("dot".to_string()));
+    }
+
+    #[test]
+    fn include_objects_from_other_files() {
+        let directory = std::env::temp_dir().join(format!("include-test-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("motifs")).unwrap();
+        let write = |file: &str, value: Value| {
+            std::fs::write(directory.join(file), value.to_string()).unwrap();
+        };
+        write(
+            "motifs/flowers.json",
+            json!({
+                "include": ["leaf.json"],
+                "objects": {
+                    "flower": {"type": "sun", "query": {"one_of_names": ["leaf", "main"]}}
+                }
+            }),
+        );
+        write(
+            "motifs/leaf.json",
+            json!({"objects": {"leaf": {"type": "circle"}}}),
+        );
+        write(
+            "input.json",
+            json!({
+                "start": {"by_name": "main"},
+                "include": [{"file": "motifs/flowers.json", "prefix": "flowers."}],
+                "objects": {"main": {"type": "circle"}}
+            }),
+        );
+        write(
+            "collision.json",
+            json!({
+                "start": {"by_name": "main"},
+                "include": ["motifs/leaf.json", "motifs/leaf.json"],
+            }),
+        );
+        write(
+            "cycle.json",
+            json!({"start": {"by_name": "main"}, "include": ["cycle.json"]}),
+        );
+
+        let structure =
+            Structure::load_from_file(directory.join("input.json").to_str().unwrap()).unwrap();
+        let mut names: Vec<&String> = structure.objects.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["flowers.flower", "flowers.leaf", "main"]);
+        match &structure.objects["flowers.flower"] {
+            Object::Sun(sun) => match &sun.query {
+                Query::OneOfNames { one_of_names, .. } => {
+                    assert_eq!(one_of_names, &vec!["flowers.leaf", "main"])
+                }
+                _ => panic!("query changed its type"),
+            },
+            _ => panic!("object changed its type"),
+        }
+
+        let collision =
+            Structure::load_from_file(directory.join("collision.json").to_str().unwrap());
+        assert!(collision
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("already defined"));
+        let cycle = Structure::load_from_file(directory.join("cycle.json").to_str().unwrap());
+        assert!(cycle.err().unwrap().to_string().contains("include cycle"));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn load_yaml_and_toml_files() {
+        let directory = std::env::temp_dir().join(format!("format-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(
+            directory.join("input.yaml"),
+            "# comments are allowed\n\
+             start: {by_name: main}\n\
+             objects:\n  \
+               main: &circle\n    type: circle\n    radius: 20\n  \
+               copy: *circle\n",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("input.toml"),
+            "width = 300\n\
+             include = [\"input.yaml\"]\n\
+             start = { by_name = \"main\" }\n\
+             [objects.ring]\ntype = \"ring\"\nradius = 10\n",
+        )
+        .unwrap();
+
+        let yaml =
+            Structure::load_from_file(directory.join("input.yaml").to_str().unwrap()).unwrap();
+        let mut names: Vec<&String> = yaml.objects.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["copy", "main"]);
+
+        let toml =
+            Structure::load_from_file(directory.join("input.toml").to_str().unwrap()).unwrap();
+        assert_eq!(toml.width, 300);
+        let mut names: Vec<&String> = toml.objects.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["copy", "main", "ring"]);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+    #[test]
+    fn load_jsonnet_files_with_variables() {
+        let directory = std::env::temp_dir().join(format!("jsonnet-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let file = directory.join("input.jsonnet");
+        std::fs::write(
+            &file,
+            "function(size) {\n\
+               start: { by_name: 'main' },\n\
+               objects: {\n\
+                 main: { type: std.extVar('shape'), radius: std.parseInt(size) * 2 },\n\
+               },\n\
+             }\n",
+        )
+        .unwrap();
+
+        let structure = Structure::load_from_jsonnet(
+            file.to_str().unwrap(),
+            &[("shape".to_string(), "ring".to_string())],
+            &[("size".to_string(), "15".to_string())],
+        )
+        .unwrap();
+        match &structure.objects["main"] {
+            Object::Ring(ring) => assert_eq!(ring.radius, 30.0),
+            _ => panic!("main should be a ring"),
+        }
+
+        // without the variables it can't be evaluated
+        assert!(Structure::load_from_jsonnet(file.to_str().unwrap(), &[], &[]).is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
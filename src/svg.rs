@@ -0,0 +1,424 @@
+//! loading of svg files, so their shapes can be drawn
+//! with the colors of the palette.
+//!
+//! Only the geometry and the colors are used:
+//! `path`, `rect`, `circle`, `ellipse`, `line`, `polyline` and `polygon`
+//! elements, nested in `g` elements with `transform` attributes.
+//! Gradients, text, clipping and embedded images are ignored.
+
+use crate::path::{self, Segment};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// A shape of an svg file, with its source colors.
+pub struct Shape {
+    /// the outline, already transformed
+    pub segments: Vec<Segment>,
+
+    /// normalized fill color (like `#ff0000`), none if not filled
+    pub fill: Option<String>,
+
+    /// normalized stroke color, none if not stroked
+    pub stroke: Option<String>,
+
+    /// fill with the `evenodd` fill rule
+    pub even_odd: bool,
+}
+
+/// All shapes of an svg file
+pub struct Drawing {
+    pub shapes: Vec<Shape>,
+
+    /// the visible area (x, y, width, height)
+    pub view_box: (f64, f64, f64, f64),
+}
+
+/// load an svg file
+pub fn load(file: &Path) -> Result<Drawing, Box<dyn Error>> {
+    let text = fs::read_to_string(file)
+        .map_err(|error| format!("can't read svg file {} : {}", file.display(), error))?;
+    parse(&text)
+        .map_err(|error| format!("can't load svg file {} : {}", file.display(), error).into())
+}
+
+/// parse the content of an svg file
+pub fn parse(text: &str) -> Result<Drawing, Box<dyn Error>> {
+    let document = roxmltree::Document::parse(text)?;
+    let root = document.root_element();
+    if root.tag_name().name() != "svg" {
+        return Err("root element is not an svg element".into());
+    }
+
+    let mut shapes = Vec::new();
+    collect(root, &Style::default(), &Matrix::identity(), &mut shapes)?;
+
+    let view_box = match root.attribute("viewBox") {
+        Some(view_box) => match path::parse_numbers(view_box)?.as_slice() {
+            [x, y, width, height] => (*x, *y, *width, *height),
+            _ => return Err(format!("invalid viewBox \"{}\"", view_box).into()),
+        },
+        None => match (
+            length(root.attribute("width")),
+            length(root.attribute("height")),
+        ) {
+            (Some(width), Some(height)) => (0.0, 0.0, width, height),
+            _ => bounds(&shapes),
+        },
+    };
+
+    Ok(Drawing { shapes, view_box })
+}
+
+/// walk through the element and its children and collect all shapes
+fn collect(
+    node: roxmltree::Node,
+    parent_style: &Style,
+    parent_matrix: &Matrix,
+    shapes: &mut Vec<Shape>,
+) -> Result<(), Box<dyn Error>> {
+    let style = parent_style.inherit(node);
+    let matrix = match node.attribute("transform") {
+        Some(transform) => parent_matrix.multiply(&parse_transform(transform)?),
+        None => parent_matrix.clone(),
+    };
+
+    let number = |name: &str| length(node.attribute(name)).unwrap_or(0.0);
+    let points = node.attribute("points").unwrap_or("");
+    let data = match node.tag_name().name() {
+        "svg" | "g" | "a" => {
+            for child in node.children().filter(|child| child.is_element()) {
+                collect(child, &style, &matrix, shapes)?;
+            }
+            return Ok(());
+        }
+        "path" => node.attribute("d").unwrap_or("").to_string(),
+        "rect" => format!(
+            "M {x} {y} h {w} v {h} h {} Z",
+            -number("width"),
+            x = number("x"),
+            y = number("y"),
+            w = number("width"),
+            h = number("height"),
+        ),
+        "circle" => ellipse(number("cx"), number("cy"), number("r"), number("r")),
+        "ellipse" => ellipse(number("cx"), number("cy"), number("rx"), number("ry")),
+        "line" => format!(
+            "M {} {} L {} {}",
+            number("x1"),
+            number("y1"),
+            number("x2"),
+            number("y2")
+        ),
+        "polyline" if !points.trim().is_empty() => format!("M {}", points),
+        "polygon" if !points.trim().is_empty() => format!("M {} Z", points),
+        // defs, clipPath, text, ... (and polygons without points) are not drawn
+        _ => return Ok(()),
+    };
+    if data.trim().is_empty() {
+        return Ok(());
+    }
+
+    let segments = path::parse(&data)
+        .map_err(|error| format!("<{}> : {}", node.tag_name().name(), error))?
+        .iter()
+        .map(|segment| matrix.apply_to(segment))
+        .collect();
+    shapes.push(Shape {
+        segments,
+        fill: style.fill,
+        stroke: style.stroke,
+        even_odd: style.even_odd,
+    });
+    Ok(())
+}
+
+/// path data of an ellipse, using two arcs
+fn ellipse(cx: f64, cy: f64, rx: f64, ry: f64) -> String {
+    format!(
+        "M {} {} A {rx} {ry} 0 1 0 {} {} A {rx} {ry} 0 1 0 {} {} Z",
+        cx + rx,
+        cy,
+        cx - rx,
+        cy,
+        cx + rx,
+        cy,
+        rx = rx,
+        ry = ry
+    )
+}
+
+/// parse a length like `100`, `100px` or `100mm` (units are ignored).
+/// percentages depend on the viewport and give no length.
+fn length(value: Option<&str>) -> Option<f64> {
+    let value = value?.trim();
+    if value.ends_with('%') {
+        return None;
+    }
+    value
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        .parse()
+        .ok()
+}
+
+/// bounding box of all shapes (x, y, width, height)
+fn bounds(shapes: &[Shape]) -> (f64, f64, f64, f64) {
+    let mut min = (f64::MAX, f64::MAX);
+    let mut max = (f64::MIN, f64::MIN);
+    for segment in shapes.iter().flat_map(|shape| shape.segments.iter()) {
+        let points = match segment {
+            Segment::MoveTo(x, y) | Segment::LineTo(x, y) => vec![(*x, *y)],
+            Segment::CurveTo(x1, y1, x2, y2, x, y) => vec![(*x1, *y1), (*x2, *y2), (*x, *y)],
+            Segment::ClosePath => vec![],
+        };
+        for (x, y) in points {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+    }
+    if min.0 > max.0 {
+        return (0.0, 0.0, 0.0, 0.0);
+    }
+    (min.0, min.1, max.0 - min.0, max.1 - min.1)
+}
+
+/// normalize a color, so `#F00`, `#ff0000`, `red` and `rgb(255,0,0)`
+/// can be matched. `none` is not a color.
+pub fn normalize_color(color: &str) -> Option<String> {
+    let color = color.trim().to_ascii_lowercase();
+    if color == "none" || color == "transparent" {
+        return None;
+    }
+    if color.starts_with('#') && color.len() == 4 {
+        return Some(
+            color
+                .chars()
+                .skip(1)
+                .fold("#".to_string(), |mut result, c| {
+                    result.push(c);
+                    result.push(c);
+                    result
+                }),
+        );
+    }
+    if color.starts_with("rgb(") && color.ends_with(')') {
+        if let Ok(numbers) = path::parse_numbers(&color[4..color.len() - 1]) {
+            if let [red, green, blue] = numbers.as_slice() {
+                return Some(format!(
+                    "#{:02x}{:02x}{:02x}",
+                    red.clamp(0.0, 255.0).round() as u8,
+                    green.clamp(0.0, 255.0).round() as u8,
+                    blue.clamp(0.0, 255.0).round() as u8
+                ));
+            }
+        }
+    }
+    let hex = match color.as_str() {
+        "black" => "#000000",
+        "white" => "#ffffff",
+        "red" => "#ff0000",
+        "lime" => "#00ff00",
+        "blue" => "#0000ff",
+        "yellow" => "#ffff00",
+        "gray" | "grey" => "#808080",
+        _ => return Some(color),
+    };
+    Some(hex.to_string())
+}
+
+/// the inherited presentation attributes
+#[derive(Clone)]
+struct Style {
+    fill: Option<String>,
+    stroke: Option<String>,
+    even_odd: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            fill: Some("#000000".to_string()),
+            stroke: None,
+            even_odd: false,
+        }
+    }
+}
+
+impl Style {
+    /// the style of the element, which overrides the style of its parent
+    fn inherit(&self, node: roxmltree::Node) -> Style {
+        let mut style = self.clone();
+        let attributes = ["fill", "stroke", "fill-rule"]
+            .iter()
+            .filter_map(|name| node.attribute(*name).map(|value| (*name, value)));
+        // the style attribute wins over presentation attributes
+        let properties = node
+            .attribute("style")
+            .unwrap_or("")
+            .split(';')
+            .filter_map(|property| {
+                let mut parts = property.splitn(2, ':');
+                Some((parts.next()?.trim(), parts.next()?.trim()))
+            });
+        for (name, value) in attributes.chain(properties) {
+            match name {
+                "fill" => style.fill = normalize_color(value),
+                "stroke" => style.stroke = normalize_color(value),
+                "fill-rule" => style.even_odd = value.trim() == "evenodd",
+                _ => (),
+            }
+        }
+        style
+    }
+}
+
+/// affine transformation (a, b, c, d, e, f) like in svg
+#[derive(Clone, Debug, PartialEq)]
+struct Matrix([f64; 6]);
+
+impl Matrix {
+    fn identity() -> Matrix {
+        Matrix([1.0, 0.0, 0.0, 1.0, 0.0, 0.0])
+    }
+
+    /// the transformation which first applies `other` and then `self`
+    fn multiply(&self, other: &Matrix) -> Matrix {
+        let [a, b, c, d, e, f] = self.0;
+        let [oa, ob, oc, od, oe, of] = other.0;
+        Matrix([
+            a * oa + c * ob,
+            b * oa + d * ob,
+            a * oc + c * od,
+            b * oc + d * od,
+            a * oe + c * of + e,
+            b * oe + d * of + f,
+        ])
+    }
+
+    fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    fn apply_to(&self, segment: &Segment) -> Segment {
+        match segment {
+            Segment::MoveTo(x, y) => {
+                let (x, y) = self.apply(*x, *y);
+                Segment::MoveTo(x, y)
+            }
+            Segment::LineTo(x, y) => {
+                let (x, y) = self.apply(*x, *y);
+                Segment::LineTo(x, y)
+            }
+            Segment::CurveTo(x1, y1, x2, y2, x, y) => {
+                let (x1, y1) = self.apply(*x1, *y1);
+                let (x2, y2) = self.apply(*x2, *y2);
+                let (x, y) = self.apply(*x, *y);
+                Segment::CurveTo(x1, y1, x2, y2, x, y)
+            }
+            Segment::ClosePath => Segment::ClosePath,
+        }
+    }
+}
+
+/// parse the `transform` attribute, like `translate(10, 10) rotate(45)`
+fn parse_transform(transform: &str) -> Result<Matrix, Box<dyn Error>> {
+    let mut matrix = Matrix::identity();
+    for part in transform.split(')') {
+        let part = part.trim().trim_start_matches(',').trim();
+        if part.is_empty() {
+            continue;
+        }
+        let mut pieces = part.splitn(2, '(');
+        let name = pieces.next().unwrap_or("").trim();
+        let arguments = path::parse_numbers(pieces.next().unwrap_or(""))?;
+        let next = match (name, arguments.as_slice()) {
+            ("matrix", [a, b, c, d, e, f]) => Matrix([*a, *b, *c, *d, *e, *f]),
+            ("translate", [x]) => Matrix([1.0, 0.0, 0.0, 1.0, *x, 0.0]),
+            ("translate", [x, y]) => Matrix([1.0, 0.0, 0.0, 1.0, *x, *y]),
+            ("scale", [s]) => Matrix([*s, 0.0, 0.0, *s, 0.0, 0.0]),
+            ("scale", [x, y]) => Matrix([*x, 0.0, 0.0, *y, 0.0, 0.0]),
+            ("rotate", [angle]) => rotation(*angle),
+            ("rotate", [angle, x, y]) => Matrix([1.0, 0.0, 0.0, 1.0, *x, *y])
+                .multiply(&rotation(*angle))
+                .multiply(&Matrix([1.0, 0.0, 0.0, 1.0, -*x, -*y])),
+            ("skewX", [angle]) => Matrix([1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0]),
+            ("skewY", [angle]) => Matrix([1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0]),
+            _ => return Err(format!("invalid transform \"{})\"", part).into()),
+        };
+        matrix = matrix.multiply(&next);
+    }
+    Ok(matrix)
+}
+
+fn rotation(angle: f64) -> Matrix {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    Matrix([cos, sin, -sin, cos, 0.0, 0.0])
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parse_shapes_with_colors_and_transforms() {
+        let drawing = parse(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24">
+                <defs><path d="M 0 0 L 1 1"/></defs>
+                <g fill="#F00" transform="translate(10 0)">
+                    <rect x="1" y="2" width="3" height="4" style="stroke: black"/>
+                    <path d="M 0 0 L 1 0 Z" fill="none" stroke="rgb(0, 0, 255)"/>
+                </g>
+                <polygon points="0,0 2,0 2,2" fill-rule="evenodd"/>
+            </svg>"##,
+        )
+        .unwrap();
+
+        assert_eq!(drawing.view_box, (0.0, 0.0, 24.0, 24.0));
+        assert_eq!(drawing.shapes.len(), 3);
+
+        let rect = &drawing.shapes[0];
+        assert_eq!(rect.fill, Some("#ff0000".to_string()));
+        assert_eq!(rect.stroke, Some("#000000".to_string()));
+        assert_eq!(rect.segments[0], Segment::MoveTo(11.0, 2.0));
+        assert_eq!(rect.segments[2], Segment::LineTo(14.0, 6.0));
+
+        let line = &drawing.shapes[1];
+        assert_eq!(line.fill, None);
+        assert_eq!(line.stroke, Some("#0000ff".to_string()));
+
+        let polygon = &drawing.shapes[2];
+        assert_eq!(polygon.fill, Some("#000000".to_string()));
+        assert!(polygon.even_odd);
+        assert_eq!(polygon.segments.last(), Some(&Segment::ClosePath));
+    }
+
+    #[test]
+    fn combine_transforms() {
+        let matrix = parse_transform("translate(10,20) scale(2) rotate(90 1 0)").unwrap();
+        let (x, y) = matrix.apply(2.0, 0.0);
+        assert!((x - 12.0).abs() < 1e-9);
+        assert!((y - 22.0).abs() < 1e-9);
+        assert!(parse_transform("wobble(3)").is_err());
+    }
+
+    #[test]
+    fn view_box_from_size_or_shapes() {
+        let sized = parse(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="30px" height="20mm">
+                <rect x="2" y="3" width="4" height="5"/>
+            </svg>"#,
+        )
+        .unwrap();
+        assert_eq!(sized.view_box, (0.0, 0.0, 30.0, 20.0));
+
+        let relative = parse(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100%" height="100%">
+                <rect x="2" y="3" width="4" height="5"/>
+            </svg>"#,
+        )
+        .unwrap();
+        assert_eq!(relative.view_box, (2.0, 3.0, 4.0, 5.0));
+    }
+}
@@ -0,0 +1,293 @@
+//! templates are objects with parameters,
+//! which are turned into objects when the structure is loaded.
+//!
+//! Every combination of template and arguments used in a query
+//! creates one object, named like `petal(radius=20)`.
+
+use crate::expression;
+use crate::objects::Object;
+use crate::structure::Query;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::value::Value;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+/// maximal number of objects created from templates,
+/// to stop templates which use themselves with different arguments.
+const MAX_INSTANCES: usize = 10_000;
+
+/// An object with parameters.
+///
+/// # Example
+///
+/// ```json
+/// "templates": {
+///   "petal": {
+///     "params": { "radius": 10, "color": "fill" },
+///     "object": { "type": "circle", "radius": "$radius", "color": "$color" }
+///   }
+/// }
+/// ```
+///
+/// and use it with
+///
+/// ```json
+/// { "by_name": "petal", "args": { "radius": 20 } }
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Template {
+    /// parameters and their default values,
+    /// use `null` for parameters without default.
+    #[serde(default)]
+    pub params: HashMap<String, Value>,
+
+    /// the object, strings like `"$radius"` are replaced by the argument `radius`
+    /// (or by the variable `radius`), expressions like `"$radius * 2"` are evaluated.
+    pub object: Value,
+}
+
+impl Template {
+    /// create the object (and its name) for the arguments
+    fn instantiate(
+        &self,
+        name: &str,
+        args: &HashMap<String, Value>,
+        vars: &HashMap<String, Value>,
+    ) -> Result<(String, Object), Box<dyn Error>> {
+        if let Some(unknown) = args.keys().find(|arg| !self.params.contains_key(*arg)) {
+            return Err(format!("template \"{}\" has no parameter \"{}\"", name, unknown).into());
+        }
+        let mut values = self.params.clone();
+        values.extend(args.iter().map(|(arg, value)| (arg.clone(), value.clone())));
+
+        let mut params: Vec<(&String, &Value)> = values.iter().collect();
+        params.sort_by(|a, b| a.0.cmp(b.0));
+        if let Some((missing, _)) = params.iter().find(|(_, value)| value.is_null()) {
+            return Err(format!("template \"{}\" needs the argument \"{}\"", name, missing).into());
+        }
+        let arguments: Vec<String> = params
+            .iter()
+            .map(|(param, value)| format!("{}={}", param, value))
+            .collect();
+        let instance = format!("{}({})", name, arguments.join(","));
+
+        // arguments hide variables with the same name
+        let mut variables = vars.clone();
+        variables.extend(values);
+        let object = expression::resolve(&self.object, &variables)
+            .map_err(|error| format!("template \"{}\" : {}", name, error))?;
+        let object: Object = serde_json::from_value(object)
+            .map_err(|error| format!("template {} : {}", instance, error))?;
+        Ok((instance, object))
+    }
+}
+
+/// Replace all queries using templates by queries of objects created from the templates.
+/// The created objects are added to `objects`,
+/// `queries` are queries which are not part of an object.
+pub fn instantiate(
+    templates: &HashMap<String, Template>,
+    vars: &HashMap<String, Value>,
+    objects: &mut HashMap<String, Object>,
+    queries: Vec<&mut Query>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(name) = templates.keys().find(|name| objects.contains_key(*name)) {
+        return Err(format!("\"{}\" is an object and a template", name).into());
+    }
+
+    let mut instances = Instances {
+        templates,
+        vars,
+        created: HashSet::new(),
+        new: Vec::new(),
+    };
+    for query in queries {
+        instances.resolve(query, objects)?;
+    }
+
+    // created objects can use templates as well
+    let mut pending: Vec<String> = objects.keys().cloned().collect();
+    loop {
+        for (name, object) in instances.new.drain(..) {
+            objects.insert(name.clone(), object);
+            pending.push(name);
+        }
+        let name = match pending.pop() {
+            Some(name) => name,
+            None => break,
+        };
+        if let Some(mut object) = objects.remove(&name) {
+            for query in object.queries_mut() {
+                instances.resolve(query, objects)?;
+            }
+            objects.insert(name, object);
+        }
+    }
+    Ok(())
+}
+
+struct Instances<'a> {
+    templates: &'a HashMap<String, Template>,
+    vars: &'a HashMap<String, Value>,
+    /// names of all created objects
+    created: HashSet<String>,
+    /// created objects, not yet added
+    new: Vec<(String, Object)>,
+}
+
+impl Instances<'_> {
+    /// change the query to use the objects created from templates
+    fn resolve(
+        &mut self,
+        query: &mut Query,
+        objects: &HashMap<String, Object>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(fallback) = query.fallback_mut() {
+            self.resolve(fallback, objects)?;
+        }
+        match query {
+            Query::ByName {
+                by_name,
+                args,
+                choose: _,
+                fallback: _,
+            } => {
+                if self.templates.contains_key(by_name) {
+                    *by_name = self.create(by_name, args, objects)?;
+                    args.clear();
+                } else if !args.is_empty() {
+                    return Err(format!("\"{}\" has args, but is not a template", by_name).into());
+                }
+            }
+            Query::OneOfNames {
+                one_of_names,
+                choose: _,
+                fallback: _,
+            } => {
+                for name in one_of_names.iter_mut() {
+                    if self.templates.contains_key(name) {
+                        *name = self.create(name, &HashMap::new(), objects)?;
+                    }
+                }
+            }
+            Query::ByTag {
+                by_tag: _,
+                choose: _,
+                fallback: _,
+            } => (),
+            Query::ByLevel {
+                until_level: _,
+                query,
+                then,
+            } => {
+                self.resolve(query, objects)?;
+                self.resolve(then, objects)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// create the object of the template (if not already created) and return its name
+    fn create(
+        &mut self,
+        template: &str,
+        args: &HashMap<String, Value>,
+        objects: &HashMap<String, Object>,
+    ) -> Result<String, Box<dyn Error>> {
+        let (name, object) = self.templates[template].instantiate(template, args, self.vars)?;
+        if !self.created.contains(&name) && !objects.contains_key(&name) {
+            if self.created.len() >= MAX_INSTANCES {
+                return Err(
+                    format!("more than {} objects created from templates", MAX_INSTANCES).into(),
+                );
+            }
+            self.created.insert(name.clone());
+            self.new.push((name.clone(), object));
+        }
+        Ok(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    fn templates() -> HashMap<String, Template> {
+        serde_json::from_value(json!({
+            "petal": {
+                "params": {"radius": 10, "color": "fill"},
+                "object": {"type": "circle", "radius": "$radius", "color": "$color"}
+            },
+            "flower": {
+                "params": {"size": null},
+                "object": {"type": "sun", "query": {"by_name": "petal", "args": {"radius": "$size"}}}
+            }
+        }))
+        .unwrap()
+    }
+
+    fn query(value: Value) -> Query {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn create_objects_for_each_argument_combination() {
+        let templates = templates();
+        let mut objects = HashMap::new();
+        let mut start = query(json!({"by_name": "flower", "args": {"size": 20}}));
+        instantiate(&templates, &HashMap::new(), &mut objects, vec![&mut start]).unwrap();
+
+        let mut names: Vec<&String> = objects.keys().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["flower(size=20)", "petal(color=\"fill\",radius=20)"]
+        );
+        match start {
+            Query::ByName { by_name, args, .. } => {
+                assert_eq!(by_name, "flower(size=20)");
+                assert!(args.is_empty());
+            }
+            _ => panic!("query changed its type"),
+        }
+    }
+
+    #[test]
+    fn wrong_arguments_are_errors() {
+        let templates = templates();
+        let mut objects = HashMap::new();
+
+        let mut missing = query(json!({"by_name": "flower"}));
+        let error = instantiate(
+            &templates,
+            &HashMap::new(),
+            &mut objects,
+            vec![&mut missing],
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("needs the argument \"size\""));
+
+        let mut unknown = query(json!({"by_name": "petal", "args": {"size": 2}}));
+        let error = instantiate(
+            &templates,
+            &HashMap::new(),
+            &mut objects,
+            vec![&mut unknown],
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("has no parameter \"size\""));
+
+        let mut no_template = query(json!({"by_name": "main", "args": {"size": 2}}));
+        let error = instantiate(
+            &templates,
+            &HashMap::new(),
+            &mut objects,
+            vec![&mut no_template],
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("is not a template"));
+    }
+}
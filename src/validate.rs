@@ -0,0 +1,604 @@
+//! checks a loaded structure for mistakes,
+//! which don't stop the rendering, but create a different image than expected
+//! (like a query for an object that doesn't exist, which draws nothing).
+//!
+//! Every problem has the path of the field it was found at,
+//! like `objects.main.query.by_name`.
+
+use crate::objects::Object;
+use crate::structure::{Query, Structure};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// the image will not look like expected
+    #[serde(rename = "error")]
+    Error,
+    /// probably a mistake, but the image is not affected
+    #[serde(rename = "warning")]
+    Warning,
+}
+
+/// a problem found in a structure
+#[derive(Serialize, Debug)]
+pub struct Problem {
+    pub severity: Severity,
+    /// path of the field, like `objects.main.query.by_name`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        if self.path.is_empty() {
+            write!(f, "{} : {}", severity, self.message)
+        } else {
+            write!(f, "{} : {} : {}", severity, self.path, self.message)
+        }
+    }
+}
+
+impl Error for Problem {}
+
+/// check the structure, errors come first
+pub fn validate(structure: &Structure) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    let mut ranges = Ranges::new("", &mut problems);
+    ranges.at_least("width", structure.width, 1);
+    ranges.at_least("height", structure.height, 1);
+    ranges.at_least("depth", structure.depth, 0);
+    ranges.positive("line_size", structure.line_size);
+    ranges.at_least("min_feature_size", structure.min_feature_size, 0.0);
+
+    let mut names: Vec<&String> = structure.objects.keys().collect();
+    names.sort();
+    for name in names.iter() {
+        structure.objects[*name].check_ranges(&mut Ranges::new(
+            &format!("objects.{}", name),
+            &mut problems,
+        ));
+    }
+
+    let queries = Queries::new(structure);
+    queries.check(&structure.start, "start", &mut problems);
+    if let Some(fallback) = &structure.fallback {
+        queries.check(fallback, "fallback", &mut problems);
+    }
+    for name in names.iter() {
+        for (path, query) in structure.objects[*name].queries() {
+            queries.check(query, &format!("objects.{}.{}", name, path), &mut problems);
+        }
+    }
+
+    let reachable =
+        queries.reachable(std::iter::once(&structure.start).chain(structure.fallback.iter()));
+    for name in names.iter().filter(|name| !reachable.contains(**name)) {
+        problems.push(Problem {
+            severity: Severity::Warning,
+            path: format!("objects.{}", name),
+            message: "is never drawn, no query of start (or of the objects it draws) finds it"
+                .to_string(),
+        });
+    }
+    let drawn = queries.drawn(&structure.start);
+    for name in names
+        .iter()
+        .filter(|name| reachable.contains(**name) && !drawn.contains(*name))
+    {
+        problems.push(Problem {
+            severity: Severity::Warning,
+            path: format!("objects.{}", name),
+            message: format!(
+                "is never drawn, the depth ({}) or the levels of recursion don't allow it",
+                structure.depth
+            ),
+        });
+    }
+    problems.extend(queries.endless_recursions(&names));
+
+    problems.sort_by_key(|problem| match problem.severity {
+        Severity::Error => 0,
+        Severity::Warning => 1,
+    });
+    problems
+}
+
+/// are there problems which are errors
+pub fn has_errors(problems: &[Problem]) -> bool {
+    problems
+        .iter()
+        .any(|problem| problem.severity == Severity::Error)
+}
+
+/// collects numbers outside of their range, as errors
+pub struct Ranges<'a> {
+    path: String,
+    problems: &'a mut Vec<Problem>,
+}
+
+impl Ranges<'_> {
+    pub fn new<'a>(path: &str, problems: &'a mut Vec<Problem>) -> Ranges<'a> {
+        Ranges {
+            path: path.to_string(),
+            problems,
+        }
+    }
+
+    /// the ranges of an object inside of this one, like `objects[2]`
+    pub fn nested(&mut self, path: &str) -> Ranges<'_> {
+        Ranges {
+            path: self.path_of(path),
+            problems: &mut *self.problems,
+        }
+    }
+
+    fn path_of(&self, field: &str) -> String {
+        if self.path.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", self.path, field)
+        }
+    }
+
+    fn error(&mut self, field: &str, message: String) {
+        let path = self.path_of(field);
+        self.problems.push(Problem {
+            severity: Severity::Error,
+            path,
+            message,
+        });
+    }
+
+    pub fn at_least<T: PartialOrd + fmt::Display>(&mut self, field: &str, value: T, min: T) {
+        // not comparable numbers (NaN) are never in range
+        if matches!(value.partial_cmp(&min), None | Some(Ordering::Less)) {
+            self.error(field, format!("must be at least {}, but is {}", min, value));
+        }
+    }
+
+    pub fn positive(&mut self, field: &str, value: f64) {
+        if value.is_nan() || value <= 0.0 {
+            self.error(field, format!("must be greater than 0, but is {}", value));
+        }
+    }
+
+    pub fn between(&mut self, field: &str, value: f64, min: f64, max: f64) {
+        if !(min <= value && value <= max) {
+            self.error(
+                field,
+                format!("must be between {} and {}, but is {}", min, max, value),
+            );
+        }
+    }
+}
+
+/// checks queries against the objects
+struct Queries<'a> {
+    objects: &'a HashMap<String, Object>,
+    tags: HashMap<&'a String, Vec<&'a String>>,
+    /// the fallback of the structure
+    fallback: Option<&'a Query>,
+    /// the depth the rendering starts with
+    depth: i32,
+}
+
+impl Queries<'_> {
+    fn new(structure: &Structure) -> Queries<'_> {
+        // sorted, so the problems are always the same
+        let mut names: Vec<&String> = structure.objects.keys().collect();
+        names.sort();
+        let mut tags: HashMap<&String, Vec<&String>> = HashMap::new();
+        for name in names {
+            for tag in structure.objects[name].get_tags().iter() {
+                tags.entry(tag).or_default().push(name);
+            }
+        }
+        Queries {
+            objects: &structure.objects,
+            tags,
+            fallback: structure.fallback.as_ref(),
+            depth: structure.depth,
+        }
+    }
+
+    fn check(&self, query: &Query, path: &str, problems: &mut Vec<Problem>) {
+        if let Query::ByLevel { query, then, .. } = query {
+            self.check(query, &format!("{}.query", path), problems);
+            self.check(then, &format!("{}.then", path), problems);
+            return;
+        }
+        let mut error = |path: String, message: String| {
+            problems.push(Problem {
+                severity: Severity::Error,
+                path,
+                message,
+            })
+        };
+        match query {
+            Query::ByName { by_name, .. } => {
+                if !self.objects.contains_key(by_name) {
+                    error(
+                        format!("{}.by_name", path),
+                        format!("unknown object \"{}\"", by_name),
+                    );
+                }
+            }
+            Query::OneOfNames { one_of_names, .. } => {
+                if one_of_names.is_empty() {
+                    error(
+                        format!("{}.one_of_names", path),
+                        "is empty, so nothing is drawn".to_string(),
+                    );
+                }
+                for (index, name) in one_of_names.iter().enumerate() {
+                    if !self.objects.contains_key(name) {
+                        error(
+                            format!("{}.one_of_names[{}]", path, index),
+                            format!("unknown object \"{}\"", name),
+                        );
+                    }
+                }
+            }
+            Query::ByTag { by_tag, .. } => {
+                if by_tag.is_empty() {
+                    error(
+                        format!("{}.by_tag", path),
+                        "is empty, so nothing is drawn".to_string(),
+                    );
+                }
+                for (index, tag) in by_tag.iter().enumerate() {
+                    if !self.tags.contains_key(tag) {
+                        error(
+                            format!("{}.by_tag[{}]", path, index),
+                            format!("no object has the tag \"{}\"", tag),
+                        );
+                    }
+                }
+            }
+            Query::ByLevel { .. } => (),
+        }
+        if let Some(fallback) = query.fallback() {
+            self.check(fallback, &format!("{}.fallback", path), problems);
+        }
+    }
+
+    /// names of all objects the query can find (but not its fallback)
+    fn names<'q>(&'q self, query: &'q Query) -> Vec<&'q String> {
+        match query {
+            Query::ByName { by_name, .. } => vec![by_name],
+            Query::OneOfNames { one_of_names, .. } => one_of_names.iter().collect(),
+            Query::ByTag { by_tag, .. } => by_tag
+                .iter()
+                .filter_map(|tag| self.tags.get(tag))
+                .flatten()
+                .cloned()
+                .collect(),
+            Query::ByLevel { query, then, .. } => {
+                let mut names = self.names(query);
+                names.extend(self.names(then));
+                names
+            }
+        }
+    }
+
+    /// names of all objects the query can find, including all fallbacks
+    fn found_by<'q>(&'q self, query: &'q Query) -> Vec<&'q String> {
+        let mut names = match query {
+            Query::ByLevel { query, then, .. } => {
+                let mut names = self.found_by(query);
+                names.extend(self.found_by(then));
+                names
+            }
+            _ => self.names(query),
+        };
+        if let Some(fallback) = query.fallback() {
+            names.extend(self.found_by(fallback));
+        }
+        names
+    }
+
+    /// the query used when the depth is reached (or objects get too small),
+    /// like `ImageContext::get_element_from_query` does.
+    fn fallback_of<'q>(&'q self, query: &'q Query) -> Option<&'q Query> {
+        query
+            .for_level(self.depth)
+            .fallback()
+            .or(self.fallback)
+            .map(|fallback| fallback.for_level(self.depth))
+    }
+
+    /// names of all objects which are drawn, when rendering starts with the query,
+    /// following the depth and the levels of recursion like the rendering does.
+    fn drawn<'q>(&'q self, start: &'q Query) -> HashSet<&'q String> {
+        let level = |depth: i32| self.depth - depth;
+        let mut pending: Vec<(&String, i32)> = if self.depth > 0 {
+            self.names(start.for_level(0))
+        } else {
+            self.fallback_of(start)
+                .map(|fallback| self.names(fallback))
+                .unwrap_or_default()
+        }
+        .into_iter()
+        .map(|name| (name, self.depth))
+        .collect();
+
+        let mut drawn = HashSet::new();
+        while let Some((name, depth)) = pending.pop() {
+            let object = match self.objects.get(name) {
+                Some(object) => object,
+                None => continue,
+            };
+            // objects drawn by the fallback don't draw other objects
+            if !drawn.insert((name, depth)) || depth < 0 {
+                continue;
+            }
+            for (_, query) in object.queries() {
+                if depth > 0 {
+                    let found = self.names(query.for_level(level(depth)));
+                    pending.extend(found.into_iter().map(|found| (found, depth - 1)));
+                }
+                if let Some(fallback) = self.fallback_of(query) {
+                    pending.extend(self.names(fallback).into_iter().map(|found| (found, -1)));
+                }
+            }
+        }
+        drawn.into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// Recursions where the objects don't get smaller,
+    /// so they are only stopped by the depth (and not by `min_feature_size`).
+    fn endless_recursions(&self, names: &[&String]) -> Vec<Problem> {
+        // queries of objects which find objects that are not smaller
+        let edges: HashMap<&String, Vec<(String, &String)>> = names
+            .iter()
+            .map(|name| {
+                let edges = self.objects[*name]
+                    .scaled_queries()
+                    .into_iter()
+                    .filter(|(_, _, scale)| *scale >= 1.0)
+                    .flat_map(|(path, query, _)| {
+                        self.names(query)
+                            .into_iter()
+                            .map(move |found| (path.clone(), found))
+                    })
+                    .collect();
+                (*name, edges)
+            })
+            .collect();
+
+        let mut problems = Vec::new();
+        let mut reported: HashSet<&String> = HashSet::new();
+        for name in names.iter() {
+            if reported.contains(*name) {
+                continue;
+            }
+            // breadth first search for the way back to the object
+            let mut came_from: HashMap<&String, &String> = HashMap::new();
+            let mut queue = VecDeque::new();
+            queue.push_back(*name);
+            'search: while let Some(current) = queue.pop_front() {
+                for (_, next) in edges.get(current).into_iter().flatten() {
+                    if came_from.contains_key(next) {
+                        continue;
+                    }
+                    came_from.insert(next, current);
+                    if next == name {
+                        break 'search;
+                    }
+                    queue.push_back(next);
+                }
+            }
+            if !came_from.contains_key(name) {
+                continue;
+            }
+
+            let mut cycle = vec![*name];
+            let mut current = came_from[name];
+            while current != *name {
+                cycle.push(current);
+                current = came_from[current];
+            }
+            cycle.push(*name);
+            cycle.reverse();
+            reported.extend(cycle.iter().cloned());
+
+            let path = edges[name]
+                .iter()
+                .find(|(_, next)| *next == cycle[1])
+                .map(|(path, _)| path.clone())
+                .unwrap_or_default();
+            let cycle: Vec<&str> = cycle.iter().map(|name| name.as_str()).collect();
+            problems.push(Problem {
+                severity: Severity::Warning,
+                path: format!("objects.{}.{}", name, path),
+                message: format!(
+                    "the objects of the recursion {} don't get smaller, only the depth stops it",
+                    cycle.join(" -> ")
+                ),
+            });
+        }
+        problems
+    }
+
+    /// names of all objects the queries (and the objects they find) can find
+    fn reachable<'q, I: Iterator<Item = &'q Query>>(&self, queries: I) -> HashSet<String> {
+        let mut reachable = HashSet::new();
+        let mut pending: Vec<&String> = queries.flat_map(|query| self.found_by(query)).collect();
+        while let Some(name) = pending.pop() {
+            let object = match self.objects.get(name) {
+                Some(object) => object,
+                None => continue,
+            };
+            if reachable.insert(name.clone()) {
+                for (_, query) in object.queries() {
+                    pending.extend(self.found_by(query));
+                }
+            }
+        }
+        reachable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    fn problems(input: serde_json::Value) -> Vec<String> {
+        let structure = Structure::load_from_value(input).unwrap();
+        validate(&structure)
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn report_problems_with_their_path() {
+        let problems = problems(json!({
+            "start": {"by_name":"main"},
+            "objects": {
+                "main": {
+                    "type": "grid",
+                    "rows": 0,
+                    "query": {"one_of_names": ["dot", "dott"], "fallback": {"by_tag": ["small"]}}
+                },
+                "dot": {"type": "circle", "radius": -5},
+                "unused": {"type": "seq", "objects": [{"type": "sun", "query": {"by_name": "x"}}]}
+            }
+        }));
+        assert_eq!(
+            problems,
+            vec![
+                "error : objects.dot.radius : must be at least 0, but is -5",
+                "error : objects.main.rows : must be at least 1, but is 0",
+                "error : objects.main.query.one_of_names[1] : unknown object \"dott\"",
+                "error : objects.main.query.fallback.by_tag[0] : no object has the tag \"small\"",
+                "error : objects.unused.objects[0].query.by_name : unknown object \"x\"",
+                "warning : objects.unused : is never drawn, no query of start (or of the objects it draws) finds it",
+            ]
+        );
+    }
+
+    #[test]
+    fn report_objects_the_depth_doesnt_reach() {
+        let problems = problems(json!({
+            "depth": 2,
+            "start": {"by_name":"main"},
+            "objects": {
+                "main": {
+                    "type": "sun",
+                    "query": {
+                        "until_level": 1,
+                        "query": {"by_name": "branch"},
+                        "then": {"by_name": "never"}
+                    }
+                },
+                "branch": {
+                    "type": "sun",
+                    "query": {"by_name": "twig", "fallback": {"by_name": "leaf"}}
+                },
+                "twig": {"type": "sun", "query": {"by_name": "deep"}},
+                "leaf": {"type": "circle"},
+                "never": {"type": "circle"},
+                "deep": {"type": "circle"}
+            }
+        }));
+        assert_eq!(
+            problems,
+            vec![
+                "warning : objects.deep : is never drawn, the depth (2) or the levels of recursion don't allow it",
+                "warning : objects.never : is never drawn, the depth (2) or the levels of recursion don't allow it",
+            ]
+        );
+    }
+
+    #[test]
+    fn report_recursions_which_dont_get_smaller() {
+        let problems = problems(json!({
+            "start": {"one_of_names": ["a", "itself", "smaller"]},
+            "objects": {
+                "a": {"type": "grid", "query": {"by_name": "b"}},
+                "b": {"type": "sun", "query": {"by_name": "a"}},
+                "itself": {"type": "grid", "query": {"by_name": "itself"}},
+                "smaller": {"type": "grid", "scale": 50, "query": {"by_name": "smaller"}}
+            }
+        }));
+        assert_eq!(
+            problems,
+            vec![
+                "warning : objects.a.query : the objects of the recursion a -> b -> a don't get smaller, only the depth stops it",
+                "warning : objects.itself.query : the objects of the recursion itself -> itself don't get smaller, only the depth stops it",
+            ]
+        );
+    }
+
+    #[test]
+    fn recursions_are_always_reported_the_same_way() {
+        for _ in 0..10 {
+            let problems = problems(json!({
+                "start": {"by_name": "a"},
+                "objects": {
+                    "a": {"type": "grid", "query": {"by_tag": ["leaf"]}},
+                    "d": {"type": "sun", "tags": ["leaf"], "query": {"by_name": "a"}},
+                    "c": {"type": "sun", "tags": ["leaf"], "query": {"by_name": "a"}},
+                    "b": {"type": "sun", "tags": ["leaf"], "query": {"by_name": "a"}}
+                }
+            }));
+            assert_eq!(
+                problems,
+                vec![
+                    "warning : objects.a.query : the objects of the recursion a -> b -> a don't get smaller, only the depth stops it",
+                    "warning : objects.c.query : the objects of the recursion c -> a -> c don't get smaller, only the depth stops it",
+                    "warning : objects.d.query : the objects of the recursion d -> a -> d don't get smaller, only the depth stops it",
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn spirals_need_a_positive_scale() {
+        let problems = problems(json!({
+            "start": {"by_name":"main"},
+            "objects": {
+                "main": {
+                    "type": "spiral",
+                    "turns": -1,
+                    "radius": -5,
+                    "scale": 0,
+                    "query": {"by_name": "dot"}
+                },
+                "dot": {"type": "circle"}
+            }
+        }));
+        assert_eq!(
+            problems,
+            vec![
+                "error : objects.main.turns : must be at least 0, but is -1",
+                "error : objects.main.radius : must be at least 0, but is -5",
+                "error : objects.main.scale : must be greater than 0, but is 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn valid_structures_have_no_problems() {
+        let problems = problems(json!({
+            "start": {"by_name":"main"},
+            "objects": {
+                "main": {"type": "sun", "query": {"by_tag": ["dot"]}},
+                "dot": {"type": "circle", "tags": ["dot"]}
+            }
+        }));
+        assert!(problems.is_empty());
+    }
+}